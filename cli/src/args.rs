@@ -0,0 +1,114 @@
+use {
+    crate::errors::CliErrors,
+    clap::Args,
+    dubstep_gossip::{
+        connection::Connection,
+        node::{GossipNode, NodeConfig},
+    },
+    solana_sdk::{
+        signature::{read_keypair_file, write_keypair_file, Keypair},
+        signer::Signer,
+    },
+    std::{
+        fs,
+        net::{SocketAddr, ToSocketAddrs},
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+};
+
+#[derive(Args, Debug)]
+pub struct NodeArgs {
+    /// Entrypoint to join through as host:port, can be repeated
+    #[arg(long, required = true)]
+    pub entrypoint: Vec<String>,
+    /// Local address to bind the gossip socket to
+    #[arg(long, default_value = "0.0.0.0:8001")]
+    pub bind: String,
+    /// Address advertised to other nodes, defaults to the bound address
+    #[arg(long)]
+    pub gossip_addr: Option<SocketAddr>,
+    /// Identity keypair file in the Solana CLI format, created on first use.
+    /// A throwaway identity is used when unset
+    #[arg(long, env = "DUBSTEP_KEYPAIR")]
+    pub keypair: Option<PathBuf>,
+    /// Shred version of the cluster, 0 accepts every cluster
+    #[arg(long, default_value_t = 0)]
+    pub shred_version: u16,
+}
+
+impl NodeArgs {
+    pub fn entrypoints(&self) -> Result<Vec<SocketAddr>, CliErrors> {
+        self.entrypoint
+            .iter()
+            .map(|entrypoint| resolve(entrypoint))
+            .collect()
+    }
+
+    // The node, not started yet so it can be set up first.
+    pub async fn build_node(&self) -> Result<Arc<GossipNode>, CliErrors> {
+        let config = NodeConfig {
+            shred_version: self.shred_version,
+            gossip_addr: self.gossip_addr,
+            entrypoints: self.entrypoints()?,
+            ..NodeConfig::default()
+        };
+        let keypair = read_keypair(self.keypair.as_deref())?;
+        let connection = Connection::new(&self.bind)
+            .await
+            .map_err(|e| CliErrors::BindFailed {
+                addr: self.bind.clone(),
+                reason: e.to_string(),
+            })?;
+
+        let node = GossipNode::new(connection, keypair, config);
+        if node
+            .contact_info()
+            .gossip()
+            .is_none_or(|addr| addr.ip().is_unspecified())
+        {
+            log::warn!("Advertising an unspecified gossip address, set --gossip-addr");
+        }
+        Ok(node)
+    }
+}
+
+// Loads the identity at `path`, generating and saving it there on the first
+// run. Without a path the identity is a throwaway one.
+pub fn read_keypair(path: Option<&Path>) -> Result<Keypair, CliErrors> {
+    let Some(path) = path else {
+        return Ok(Keypair::new());
+    };
+    if !path.exists() {
+        let keypair = Keypair::new();
+        save_keypair(&keypair, path)?;
+        log::info!(
+            "Saved new identity {} to {}",
+            keypair.pubkey(),
+            path.display()
+        );
+        return Ok(keypair);
+    }
+    read_keypair_file(path).map_err(|e| CliErrors::InvalidKeypair {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })
+}
+
+// Writes through a temporary file so a crash never leaves a partial key.
+pub fn save_keypair(keypair: &Keypair, path: &Path) -> Result<(), CliErrors> {
+    let write_failed = |reason: String| CliErrors::WriteFailed {
+        path: path.display().to_string(),
+        reason,
+    };
+    let tmp = path.with_extension("tmp");
+    write_keypair_file(keypair, &tmp).map_err(|e| write_failed(e.to_string()))?;
+    fs::rename(&tmp, path).map_err(|e| write_failed(e.to_string()))
+}
+
+pub fn resolve(addr: &str) -> Result<SocketAddr, CliErrors> {
+    addr.to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.find(SocketAddr::is_ipv4))
+        .ok_or_else(|| CliErrors::InvalidAddress(addr.to_string()))
+}
@@ -0,0 +1,331 @@
+use {
+    crate::errors::CliErrors,
+    base64::{engine::general_purpose::STANDARD, Engine},
+    clap::{Args, ValueEnum},
+    dubstep_gossip::{
+        gossip_data::{
+            contact_info::socket_tag_name,
+            filter::DataFilter,
+            gossip_data::{GossipData, GossipValue},
+            sanitize::Sanitize,
+        },
+        protocol::Protocol,
+        prune::PruneData,
+    },
+    solana_sdk::signature::Signable,
+    std::{
+        fmt::{Debug, Write},
+        fs,
+        io::{self, Read},
+        path::Path,
+    },
+};
+
+#[derive(Args, Debug)]
+pub struct DecodeArgs {
+    /// Packet file, `-` for stdin, or the encoded packet itself
+    #[arg(default_value = "-")]
+    input: String,
+    /// Encoding of the input
+    #[arg(long, value_enum, default_value_t = Format::Auto)]
+    format: Format,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Hex or base64 if the input is valid text in either, raw otherwise
+    Auto,
+    Hex,
+    Base64,
+    Raw,
+}
+
+pub fn run(args: DecodeArgs) -> Result<(), CliErrors> {
+    let input = read_input(&args.input)?;
+    let packet = decode_input(&input, args.format)?;
+    let (protocol, offset) = decode_packet(&packet)?;
+    print!("{}", render(&protocol));
+    if offset < packet.len() {
+        println!(
+            "{} trailing bytes after offset {}",
+            packet.len() - offset,
+            offset
+        );
+    }
+    Ok(())
+}
+
+fn read_input(input: &str) -> Result<Vec<u8>, CliErrors> {
+    let read_failed = |e: io::Error| CliErrors::InvalidInput(format!("{}: {}", input, e));
+    if input == "-" {
+        let mut buf = vec![];
+        io::stdin().read_to_end(&mut buf).map_err(read_failed)?;
+        return Ok(buf);
+    }
+    if Path::new(input).is_file() {
+        return fs::read(input).map_err(read_failed);
+    }
+    Ok(input.as_bytes().to_vec())
+}
+
+fn decode_input(input: &[u8], format: Format) -> Result<Vec<u8>, CliErrors> {
+    let text = || {
+        std::str::from_utf8(input)
+            .map(|text| text.split_whitespace().collect::<String>())
+            .map_err(|_| CliErrors::InvalidInput("input is not text".to_string()))
+    };
+    match format {
+        Format::Raw => Ok(input.to_vec()),
+        Format::Hex => {
+            let text = text()?;
+            let text = text.strip_prefix("0x").unwrap_or(&text);
+            hex::decode(text).map_err(|e| CliErrors::InvalidInput(format!("bad hex: {}", e)))
+        }
+        Format::Base64 => STANDARD
+            .decode(text()?)
+            .map_err(|e| CliErrors::InvalidInput(format!("bad base64: {}", e))),
+        Format::Auto => Ok(decode_input(input, Format::Hex)
+            .or_else(|_| decode_input(input, Format::Base64))
+            .unwrap_or_else(|_| input.to_vec())),
+    }
+}
+
+// Returns the message and how many bytes it took. On failure, reports the
+// offset bincode had reached.
+fn decode_packet(packet: &[u8]) -> Result<(Protocol, usize), CliErrors> {
+    let mut reader = packet;
+    let result = bincode::deserialize_from(&mut reader);
+    let offset = packet.len() - reader.len();
+    match result {
+        Ok(protocol) => Ok((protocol, offset)),
+        Err(e) => Err(CliErrors::Decode {
+            offset,
+            len: packet.len(),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+fn render(protocol: &Protocol) -> String {
+    let mut out = String::new();
+    let mut tree = Tree::new(&mut out);
+    match protocol {
+        Protocol::PullRequest(filter, value) => {
+            tree.line("PullRequest");
+            tree.nested(|tree| {
+                tree.line("filter");
+                tree.nested(|tree| render_filter(tree, filter));
+                tree.line("value");
+                tree.nested(|tree| render_value(tree, value));
+            });
+        }
+        Protocol::PullResponse(from, values) => {
+            tree.line("PullResponse");
+            tree.nested(|tree| {
+                tree.field("from", from);
+                render_values(tree, values);
+            });
+        }
+        Protocol::PushMessage(from, values) => {
+            tree.line("PushMessage");
+            tree.nested(|tree| {
+                tree.field("from", from);
+                render_values(tree, values);
+            });
+        }
+        Protocol::PruneMessage(from, data) => {
+            tree.line("PruneMessage");
+            tree.nested(|tree| {
+                tree.field("from", from);
+                render_prune(tree, data);
+            });
+        }
+        Protocol::PingMessage(ping) => {
+            tree.line("Ping");
+            tree.nested(|tree| {
+                tree.field("from", ping.from());
+                tree.field("signature", verified(ping.verify()));
+            });
+        }
+        Protocol::PongMessage(pong) => {
+            tree.line("Pong");
+            tree.nested(|tree| {
+                tree.field("from", pong.from());
+                tree.field("hash", pong.hash());
+                tree.field("signature", verified(pong.verify()));
+            });
+        }
+    }
+    if let Err(e) = protocol.sanitize() {
+        tree.field("sanitize", format!("failed: {}", e));
+    }
+    out
+}
+
+fn render_filter(tree: &mut Tree, filter: &DataFilter) {
+    let (bits, set) = (filter.num_bits(), filter.num_bits_set());
+    tree.field("mask", format!("{:#018x}", filter.mask()));
+    tree.field("mask_bits", filter.mask_bits());
+    tree.field("keys", filter.filter.keys.len());
+    tree.field(
+        "bits",
+        format!(
+            "{} set of {} ({:.1}%)",
+            set,
+            bits,
+            set as f64 * 100.0 / bits.max(1) as f64
+        ),
+    );
+}
+
+fn render_values(tree: &mut Tree, values: &[GossipValue]) {
+    tree.field("values", values.len());
+    for (i, value) in values.iter().enumerate() {
+        tree.line(format!("[{}]", i));
+        tree.nested(|tree| render_value(tree, value));
+    }
+}
+
+fn render_value(tree: &mut Tree, value: &GossipValue) {
+    tree.field("label", format!("{:?}", value.label()));
+    tree.field("signature", verified(value.verify()));
+    tree.field("wallclock", value.wallclock());
+    match &value.data {
+        GossipData::ContactInfo(contact_info) => {
+            tree.field("shred_version", contact_info.shred_version());
+            tree.field("version", contact_info.version());
+            tree.line("sockets");
+            tree.nested(|tree| {
+                for (tag, addr) in contact_info.socket_addrs() {
+                    match socket_tag_name(tag) {
+                        Some(name) => tree.field(name, addr),
+                        None => tree.field(&format!("tag {}", tag), addr),
+                    }
+                }
+            });
+        }
+        GossipData::LegacyContactInfo(contact_info) => tree.debug(contact_info),
+        GossipData::Vote(_, vote) => {
+            tree.field("slots", format!("{:?}", vote.slots()));
+            if let Some(signature) = vote.transaction().signatures.first() {
+                tree.field("transaction", signature);
+            }
+        }
+        GossipData::Version(version) => tree.field("version", &version.version),
+        GossipData::LegacyVersion(version) => tree.debug(&version.version),
+        data => tree.debug(data),
+    }
+}
+
+fn render_prune(tree: &mut Tree, data: &PruneData) {
+    tree.field("pubkey", data.pubkey);
+    tree.field("destination", data.destination);
+    tree.field("wallclock", data.wallclock);
+    tree.field("signature", verified(data.verify()));
+    tree.field("prunes", data.prunes.len());
+    tree.nested(|tree| {
+        for pubkey in &data.prunes {
+            tree.line(pubkey);
+        }
+    });
+}
+
+fn verified(ok: bool) -> &'static str {
+    if ok {
+        "verified"
+    } else {
+        "INVALID"
+    }
+}
+
+struct Tree<'a> {
+    out: &'a mut String,
+    depth: usize,
+}
+
+impl<'a> Tree<'a> {
+    fn new(out: &'a mut String) -> Self {
+        Self { out, depth: 0 }
+    }
+
+    fn line(&mut self, line: impl std::fmt::Display) {
+        let _ = writeln!(self.out, "{:indent$}{}", "", line, indent = self.depth * 2);
+    }
+
+    fn field(&mut self, name: &str, value: impl std::fmt::Display) {
+        self.line(format_args!("{}: {}", name, value));
+    }
+
+    // Pretty debug output for data without a dedicated layout.
+    fn debug(&mut self, value: &impl Debug) {
+        for line in format!("{:#?}", value).lines() {
+            self.line(line);
+        }
+    }
+
+    fn nested(&mut self, f: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        bincode::serialize,
+        dubstep_gossip::gossip_data::contact_info::ContactInfo,
+        solana_sdk::{signature::Keypair, signer::Signer, timing::timestamp},
+    };
+
+    fn push_message() -> (Vec<u8>, GossipValue) {
+        let keypair = Keypair::new();
+        let gossip = "10.0.0.1:8001".parse().unwrap();
+        let contact_info = ContactInfo::new(keypair.pubkey(), timestamp(), 0, gossip);
+        let value = GossipValue::new_signed(GossipData::ContactInfo(contact_info), &keypair);
+        let message = Protocol::PushMessage(keypair.pubkey(), vec![value.clone()]);
+        (serialize(&message).unwrap(), value)
+    }
+
+    #[test]
+    fn test_decode_input() {
+        let (packet, _) = push_message();
+        let hex = hex::encode(&packet);
+        let base64 = STANDARD.encode(&packet);
+
+        assert_eq!(decode_input(hex.as_bytes(), Format::Auto).unwrap(), packet);
+        assert_eq!(
+            decode_input(base64.as_bytes(), Format::Auto).unwrap(),
+            packet
+        );
+        assert_eq!(decode_input(&packet, Format::Auto).unwrap(), packet);
+        assert!(decode_input(&packet, Format::Hex).is_err());
+    }
+
+    #[test]
+    fn test_render_push_message() {
+        let (packet, value) = push_message();
+        let (protocol, offset) = decode_packet(&packet).unwrap();
+        assert_eq!(offset, packet.len());
+
+        let out = render(&protocol);
+        assert!(out.starts_with("PushMessage\n"));
+        assert!(out.contains(&format!("from: {}", value.pubkey())));
+        assert!(out.contains("signature: verified"));
+        assert!(out.contains("gossip: 10.0.0.1:8001"));
+        assert!(!out.contains("sanitize"));
+    }
+
+    #[test]
+    fn test_decode_truncated_packet() {
+        let (packet, _) = push_message();
+        match decode_packet(&packet[..60]) {
+            Err(CliErrors::Decode { offset, len, .. }) => {
+                assert_eq!(len, 60);
+                assert!(offset > 40 && offset <= 60, "offset {}", offset);
+            }
+            _ => panic!("Decoded a truncated packet"),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use {std::net::SocketAddr, thiserror::Error};
+
+#[derive(Debug, Error)]
+pub enum CliErrors {
+    #[error("Failed to resolve {0}")]
+    InvalidAddress(String),
+    #[error("Failed to read keypair {path}: {reason}")]
+    InvalidKeypair { path: String, reason: String },
+    #[error("Failed to bind {addr}: {reason}")]
+    BindFailed { addr: String, reason: String },
+    #[error("Failed to create ping: {0}")]
+    Ping(String),
+    #[error("Invalid input, {0}")]
+    InvalidInput(String),
+    #[error("Failed to decode at byte {offset} of {len}: {reason}")]
+    Decode {
+        offset: usize,
+        len: usize,
+        reason: String,
+    },
+    #[error("Failed to write {path}: {reason}")]
+    WriteFailed { path: String, reason: String },
+    #[cfg(feature = "sqlite")]
+    #[error("Failed to open store {path}: {reason}")]
+    Store { path: String, reason: String },
+    #[error("Failed to send to {addr}: {reason}")]
+    SendFailed { addr: SocketAddr, reason: String },
+    #[error("Failed to receive from {addr}: {reason}")]
+    RecvFailed { addr: SocketAddr, reason: String },
+}
@@ -0,0 +1,52 @@
+use {
+    clap::{Parser, Subcommand},
+    std::process::ExitCode,
+};
+
+mod args;
+mod decode;
+mod errors;
+mod ping;
+mod replay;
+mod spy;
+
+#[derive(Parser)]
+#[command(name = "dubstep", version, about = "Solana gossip client")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Join the cluster and print a live table of known nodes
+    Spy(Box<spy::SpyArgs>),
+    /// Send signed pings to a gossip address and time the pongs
+    Ping(ping::PingArgs),
+    /// Decode a captured gossip packet and print its contents
+    Decode(decode::DecodeArgs),
+    /// Feed a recorded capture through message processing and report the outcome
+    Replay(replay::ReplayArgs),
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // A `.env` file can set the same variables as the environment.
+    dotenv::dotenv().ok();
+    env_logger::init();
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Spy(args) => spy::run(*args).await,
+        Command::Ping(args) => ping::run(args).await,
+        Command::Decode(args) => decode::run(args),
+        Command::Replay(args) => replay::run(args).await,
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
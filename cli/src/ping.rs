@@ -0,0 +1,207 @@
+use {
+    crate::{
+        args::{read_keypair, resolve},
+        errors::CliErrors,
+    },
+    bincode::{deserialize, serialize},
+    clap::Args,
+    dubstep_gossip::{
+        ping_pong::{Ping, Pong},
+        protocol::Protocol,
+    },
+    solana_sdk::{hash::Hash, packet::PACKET_DATA_SIZE, signature::Keypair},
+    std::{
+        fmt,
+        net::SocketAddr,
+        path::PathBuf,
+        time::{Duration, Instant},
+    },
+    tokio::{net::UdpSocket, time::timeout},
+};
+
+#[derive(Args, Debug)]
+pub struct PingArgs {
+    /// Gossip address of the node to ping, as host:port
+    addr: String,
+    /// Number of pings to send
+    #[arg(short, long, default_value_t = 4)]
+    count: u32,
+    /// Milliseconds between pings
+    #[arg(long, default_value_t = 1000)]
+    interval: u64,
+    /// Milliseconds to wait for each pong
+    #[arg(long, default_value_t = 1000)]
+    timeout: u64,
+    /// Local address to send from
+    #[arg(long, default_value = "0.0.0.0:0")]
+    bind: String,
+    /// Identity keypair file in the Solana CLI format, created on first use.
+    /// A throwaway identity is used when unset
+    #[arg(long, env = "DUBSTEP_KEYPAIR")]
+    keypair: Option<PathBuf>,
+}
+
+pub async fn run(args: PingArgs) -> Result<(), CliErrors> {
+    let addr = resolve(&args.addr)?;
+    let keypair = read_keypair(args.keypair.as_deref())?;
+    let socket = UdpSocket::bind(&args.bind)
+        .await
+        .map_err(|e| CliErrors::BindFailed {
+            addr: args.bind.clone(),
+            reason: e.to_string(),
+        })?;
+
+    println!("PING {} from {}", addr, socket.local_addr().unwrap());
+    let mut stats = PingStats::default();
+    for seq in 0..args.count {
+        if seq > 0 {
+            tokio::time::sleep(Duration::from_millis(args.interval)).await;
+        }
+        stats.sent += 1;
+        match ping_once(&socket, &keypair, addr, Duration::from_millis(args.timeout)).await? {
+            Some((pong, rtt)) => {
+                println!(
+                    "pong from {} ({}): seq={} time={:.2} ms",
+                    pong.from(),
+                    addr,
+                    seq,
+                    rtt.as_secs_f64() * 1000.0
+                );
+                stats.rtts.push(rtt);
+            }
+            None => println!("timeout: seq={}", seq),
+        }
+    }
+    println!("--- {} ping statistics ---\n{}", addr, stats);
+    Ok(())
+}
+
+// Sends one ping and waits for the pong answering it. Anything else arriving
+// on the socket, including pongs with a bad signature, is ignored. A failed
+// receive ends the wait with an error.
+async fn ping_once(
+    socket: &UdpSocket,
+    keypair: &Keypair,
+    addr: SocketAddr,
+    wait: Duration,
+) -> Result<Option<(Pong, Duration)>, CliErrors> {
+    let ping = Ping::rand(keypair).map_err(|e| CliErrors::Ping(e.to_string()))?;
+    let hash = ping
+        .pong_hash()
+        .map_err(|e| CliErrors::Ping(e.to_string()))?;
+    let message =
+        serialize(&Protocol::PingMessage(ping)).map_err(|e| CliErrors::Ping(e.to_string()))?;
+
+    let start = Instant::now();
+    socket
+        .send_to(&message, addr)
+        .await
+        .map_err(|e| CliErrors::SendFailed {
+            addr,
+            reason: e.to_string(),
+        })?;
+
+    let mut buf = vec![0u8; PACKET_DATA_SIZE];
+    let pong = timeout(wait, async {
+        loop {
+            let received = socket.recv_from(&mut buf).await;
+            let (len, from) = received.map_err(|e| CliErrors::RecvFailed {
+                addr,
+                reason: e.to_string(),
+            })?;
+            if let Some(pong) = matching_pong(&buf[..len], from, addr, &hash) {
+                return Ok(pong);
+            }
+        }
+    })
+    .await;
+    match pong {
+        Ok(pong) => Ok(Some((pong?, start.elapsed()))),
+        Err(_) => Ok(None),
+    }
+}
+
+fn matching_pong(packet: &[u8], from: SocketAddr, addr: SocketAddr, hash: &Hash) -> Option<Pong> {
+    if from != addr {
+        return None;
+    }
+    match deserialize(packet) {
+        Ok(Protocol::PongMessage(pong)) if pong.hash() == hash => {
+            if pong.verify() {
+                return Some(pong);
+            }
+            log::warn!("Pong from {} has an invalid signature", from);
+            None
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default)]
+struct PingStats {
+    sent: u32,
+    rtts: Vec<Duration>,
+}
+
+impl fmt::Display for PingStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let received = self.rtts.len() as u32;
+        let loss = match self.sent {
+            0 => 0.0,
+            sent => f64::from(sent - received) * 100.0 / f64::from(sent),
+        };
+        write!(
+            f,
+            "{} transmitted, {} received, {:.1}% loss",
+            self.sent, received, loss
+        )?;
+        let ms = |rtt: Duration| rtt.as_secs_f64() * 1000.0;
+        if let (Some(min), Some(max)) = (self.rtts.iter().min(), self.rtts.iter().max()) {
+            let avg = self.rtts.iter().sum::<Duration>() / received;
+            write!(
+                f,
+                "\nrtt min/avg/max = {:.2}/{:.2}/{:.2} ms",
+                ms(*min),
+                ms(avg),
+                ms(*max)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_pong() {
+        let addr: SocketAddr = "127.0.0.1:8001".parse().unwrap();
+        let ping = Ping::rand(&Keypair::new()).unwrap();
+        let hash = ping.pong_hash().unwrap();
+        let pong = serialize(&Protocol::PongMessage(
+            Pong::new(&ping, &Keypair::new()).unwrap(),
+        ))
+        .unwrap();
+
+        assert!(matching_pong(&pong, addr, addr, &hash).is_some());
+        assert!(matching_pong(&pong, "127.0.0.1:8002".parse().unwrap(), addr, &hash).is_none());
+        assert!(matching_pong(&pong, addr, addr, &Hash::default()).is_none());
+    }
+
+    #[test]
+    fn test_ping_stats() {
+        let stats = PingStats {
+            sent: 4,
+            rtts: vec![Duration::from_millis(10), Duration::from_millis(30)],
+        };
+        assert_eq!(
+            stats.to_string(),
+            "4 transmitted, 2 received, 50.0% loss\nrtt min/avg/max = 10.00/20.00/30.00 ms"
+        );
+        assert_eq!(
+            PingStats::default().to_string(),
+            "0 transmitted, 0 received, 0.0% loss"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use {
+    crate::{errors::CliErrors, spy::write_snapshot},
+    clap::Args,
+    dubstep_gossip::{
+        connection::{Connection, DropReason},
+        error::GossipErrorKind,
+        node::{GossipNode, NodeConfig},
+        transport::replay::ReplayTransport,
+        wallclock::WallclockWindow,
+    },
+    solana_sdk::signature::Keypair,
+    std::{path::PathBuf, sync::Arc, time::Duration},
+    tokio::time::Instant,
+};
+
+// How long the node must stay idle after the last packet before the replay
+// counts as done.
+const SETTLE_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// Capture file recorded with `dubstep spy --capture`
+    capture: PathBuf,
+    /// Playback speed, 1 keeps the recorded timing. As fast as possible when unset
+    #[arg(long)]
+    speed: Option<f64>,
+    /// Only accept values with this shred version, 0 accepts any
+    #[arg(long, default_value_t = 0)]
+    shred_version: u16,
+    /// Write a JSON snapshot of the resulting gossip table to this file
+    #[arg(long)]
+    dump: Option<PathBuf>,
+}
+
+pub async fn run(args: ReplayArgs) -> Result<(), CliErrors> {
+    let transport = ReplayTransport::open(&args.capture, args.speed)
+        .map_err(|e| CliErrors::InvalidInput(format!("{}: {}", args.capture.display(), e)))?;
+    let transport = Arc::new(transport);
+    let config = NodeConfig {
+        shred_version: args.shred_version,
+        // Captured values are as old as the capture, accept them anyway.
+        wallclock_window: WallclockWindow {
+            push_ms: u64::MAX,
+            pull_ms: u64::MAX,
+        },
+        ..NodeConfig::default()
+    };
+    let connection = Connection::with_transport(transport.clone());
+    let node = GossipNode::new(connection, Keypair::new(), config);
+
+    let start = Instant::now();
+    node.start_processing();
+    transport.finished().await;
+    let mut last = progress(&node);
+    let mut done = Instant::now();
+    loop {
+        tokio::time::sleep(SETTLE_INTERVAL).await;
+        let next = progress(&node);
+        if next == last {
+            break;
+        }
+        last = next;
+        done = Instant::now();
+    }
+    let elapsed = done - start;
+
+    let traffic = node.connection.traffic_in();
+    println!(
+        "Replayed {} packets ({} bytes) in {:.3}s, {:.0} packets/s",
+        traffic.packets(),
+        traffic.bytes(),
+        elapsed.as_secs_f64(),
+        traffic.packets() as f64 / elapsed.as_secs_f64().max(1e-3),
+    );
+    println!(
+        "Table has {} values, signatures {} verified {} failed",
+        node.table.read().unwrap().len(),
+        node.verify_stats.verified(),
+        node.verify_stats.failed(),
+    );
+    for kind in GossipErrorKind::ALL {
+        let count = node.errors.get(kind);
+        if count > 0 {
+            println!("  error {}: {}", kind.as_str(), count);
+        }
+    }
+    for reason in DropReason::ALL {
+        let count = node.connection.drops().get(reason);
+        if count > 0 {
+            println!("  drop {}: {}", reason.as_str(), count);
+        }
+    }
+    if let Some(path) = &args.dump {
+        let snapshot = node.table.read().unwrap().snapshot_json();
+        write_snapshot(path, &snapshot)?;
+    }
+    Ok(())
+}
+
+// Changes while messages are still being processed.
+fn progress(node: &GossipNode) -> (usize, u64, u64) {
+    (
+        node.table.read().unwrap().len(),
+        node.errors.total(),
+        node.verify_stats.verified() + node.verify_stats.failed(),
+    )
+}
@@ -0,0 +1,304 @@
+use {
+    crate::{
+        args::{save_keypair, NodeArgs},
+        errors::CliErrors,
+    },
+    clap::Args,
+    dubstep_gossip::{
+        capture::CaptureWriter,
+        gossip_data::gossip_data::{GossipData, GossipLabel},
+        gossip_table::GossipTable,
+    },
+    solana_sdk::{pubkey::Pubkey, signature::Keypair, timing::timestamp},
+    std::{
+        collections::HashMap,
+        fmt::Write,
+        fs,
+        net::SocketAddr,
+        path::{Path, PathBuf},
+        time::Duration,
+    },
+};
+
+#[derive(Args, Debug)]
+pub struct SpyArgs {
+    #[command(flatten)]
+    node: NodeArgs,
+    /// Seconds between table refreshes
+    #[arg(long, default_value_t = 2)]
+    refresh: u64,
+    /// Write a JSON snapshot of the gossip table to this file on every refresh
+    #[arg(long)]
+    dump: Option<PathBuf>,
+    /// Serve the HTTP API on this address
+    #[cfg(feature = "http")]
+    #[arg(long)]
+    http: Option<SocketAddr>,
+    /// Serve Prometheus metrics at /metrics on this address
+    #[cfg(feature = "http")]
+    #[arg(long)]
+    metrics: Option<SocketAddr>,
+    /// Switch to a new identity every this many seconds. The keypair file is
+    /// never overwritten, each new key is saved next to it as
+    /// `<name>.rotated-<timestamp>.json`
+    #[arg(long)]
+    rotate_identity: Option<u64>,
+    /// Record every datagram sent and received to this capture file
+    #[arg(long)]
+    capture: Option<PathBuf>,
+    /// Record every accepted gossip value in this SQLite database
+    #[cfg(feature = "sqlite")]
+    #[arg(long)]
+    store: Option<PathBuf>,
+    /// Only record these tables, e.g. contact_info,vote
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_delimiter = ',', requires = "store")]
+    store_tables: Vec<String>,
+    /// Load the latest stored values into the table before joining
+    #[cfg(feature = "sqlite")]
+    #[arg(long, requires = "store")]
+    warm_start: bool,
+}
+
+pub async fn run(args: SpyArgs) -> Result<(), CliErrors> {
+    let node = args.node.build_node().await?;
+    if let Some(path) = &args.capture {
+        let capture = node
+            .connection
+            .local_addr()
+            .and_then(|addr| CaptureWriter::create(path, addr))
+            .map_err(|e| CliErrors::WriteFailed {
+                path: path.display().to_string(),
+                reason: e.to_string(),
+            })?;
+        let _ = node.connection.set_capture(capture);
+    }
+    #[cfg(feature = "sqlite")]
+    let store = match &args.store {
+        Some(path) => Some(open_store(
+            &node,
+            path,
+            &args.store_tables,
+            args.warm_start,
+        )?),
+        None => None,
+    };
+    node.start();
+    println!("Spying as {}", node.pubkey());
+    #[cfg(feature = "http")]
+    if let Some(addr) = args.http {
+        let node = node.clone();
+        tokio::spawn(async move {
+            if let Err(e) = dubstep_gossip::http::serve(node, addr).await {
+                log::error!("HTTP API on {} failed: {}", addr, e);
+            }
+        });
+    }
+    #[cfg(feature = "http")]
+    if let Some(addr) = args.metrics {
+        let node = node.clone();
+        tokio::spawn(async move {
+            if let Err(e) = dubstep_gossip::http::serve_metrics(node, addr).await {
+                log::error!("Metrics on {} failed: {}", addr, e);
+            }
+        });
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(args.refresh.max(1)));
+    let mut rotate = args.rotate_identity.map(|secs| {
+        let period = Duration::from_secs(secs.max(1));
+        tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+    });
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let (rows, snapshot) = {
+                    let table = node.table.read().unwrap();
+                    (node_rows(&table), args.dump.as_ref().map(|_| table.snapshot_json()))
+                };
+                print!("\x1b[2J\x1b[H{}", render(&rows, timestamp()));
+                if let (Some(path), Some(snapshot)) = (&args.dump, snapshot) {
+                    write_snapshot(path, &snapshot)?;
+                }
+            }
+            _ = async { rotate.as_mut().unwrap().tick().await }, if rotate.is_some() => {
+                let keypair = Keypair::new();
+                if let Some(path) = &args.node.keypair {
+                    let rotated = path.with_extension(format!("rotated-{}.json", timestamp()));
+                    save_keypair(&keypair, &rotated)?;
+                }
+                node.set_keypair(keypair);
+            }
+            _ = tokio::signal::ctrl_c() => {
+                node.connection.close_capture();
+                #[cfg(feature = "sqlite")]
+                if let Some(store) = &store {
+                    store.close();
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn open_store(
+    node: &dubstep_gossip::node::GossipNode,
+    path: &Path,
+    tables: &[String],
+    warm_start: bool,
+) -> Result<std::sync::Arc<dubstep_gossip::store::StoreWriter>, CliErrors> {
+    use dubstep_gossip::store::{parse_table, GossipStore};
+    let store_failed = |e: dubstep_gossip::store::StoreErrors| CliErrors::Store {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    };
+    let tables = match tables {
+        [] => None,
+        tables => Some(
+            tables
+                .iter()
+                .map(|table| parse_table(table))
+                .collect::<Result<_, _>>()
+                .map_err(|e| CliErrors::InvalidInput(e.to_string()))?,
+        ),
+    };
+    let store = GossipStore::open(path).map_err(store_failed)?;
+    if warm_start {
+        let loaded = store
+            .warm_start(&mut node.table.write().unwrap(), timestamp())
+            .map_err(store_failed)?;
+        log::info!("Loaded {} values from {}", loaded, path.display());
+    }
+    let writer = std::sync::Arc::new(store.spawn_writer(tables));
+    node.add_handler(writer.clone());
+    Ok(writer)
+}
+
+// Writes through a temporary file so readers never see a partial dump.
+pub fn write_snapshot(path: &Path, snapshot: &serde_json::Value) -> Result<(), CliErrors> {
+    let write_failed = |e: std::io::Error| CliErrors::WriteFailed {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    };
+    let tmp = path.with_extension("tmp");
+    let bytes = serde_json::to_vec_pretty(snapshot).map_err(|e| write_failed(e.into()))?;
+    fs::write(&tmp, bytes).map_err(write_failed)?;
+    fs::rename(&tmp, path).map_err(write_failed)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct NodeRow {
+    pubkey: Pubkey,
+    gossip: Option<SocketAddr>,
+    tpu: Option<SocketAddr>,
+    rpc: Option<SocketAddr>,
+    version: Option<String>,
+    shred_version: u16,
+    last_seen: u64,
+}
+
+// One row per node, from its contact info. Nodes only advertising the legacy
+// contact info take their version from the separate version value.
+fn node_rows(table: &GossipTable) -> Vec<NodeRow> {
+    let mut rows: HashMap<Pubkey, NodeRow> = HashMap::new();
+    for entry in table.entries() {
+        let row = match &entry.value.data {
+            GossipData::ContactInfo(contact_info) => NodeRow {
+                pubkey: *contact_info.pubkey(),
+                gossip: contact_info.gossip(),
+                tpu: contact_info.tpu().or_else(|| contact_info.tpu_quic()),
+                rpc: contact_info.rpc(),
+                version: Some(contact_info.version().to_string()),
+                shred_version: contact_info.shred_version(),
+                last_seen: entry.local_timestamp,
+            },
+            GossipData::LegacyContactInfo(contact_info) => {
+                if rows.contains_key(contact_info.pubkey()) {
+                    continue;
+                }
+                let version = table
+                    .get(&GossipLabel::Version(*contact_info.pubkey()))
+                    .and_then(|entry| match &entry.value.data {
+                        GossipData::Version(version) => Some(version.version.to_string()),
+                        _ => None,
+                    });
+                NodeRow {
+                    pubkey: *contact_info.pubkey(),
+                    gossip: contact_info.gossip(),
+                    tpu: contact_info.tpu(),
+                    rpc: contact_info.rpc(),
+                    version,
+                    shred_version: contact_info.shred_version(),
+                    last_seen: entry.local_timestamp,
+                }
+            }
+            _ => continue,
+        };
+        rows.insert(row.pubkey, row);
+    }
+
+    let mut rows: Vec<NodeRow> = rows.into_values().collect();
+    rows.sort_unstable_by_key(|row| (std::cmp::Reverse(row.last_seen), row.pubkey));
+    rows
+}
+
+fn render(rows: &[NodeRow], now: u64) -> String {
+    let addr = |addr: Option<SocketAddr>| addr.map_or_else(|| "-".to_string(), |a| a.to_string());
+    let mut out = format!("{} nodes\n", rows.len());
+    let _ = writeln!(
+        out,
+        "{:<44} {:<21} {:<21} {:<21} {:<10} {:>6} {:>9}",
+        "Pubkey", "Gossip", "TPU", "RPC", "Version", "Shred", "Last seen"
+    );
+    for row in rows {
+        let _ = writeln!(
+            out,
+            "{:<44} {:<21} {:<21} {:<21} {:<10} {:>6} {:>8}s",
+            row.pubkey.to_string(),
+            addr(row.gossip),
+            addr(row.tpu),
+            addr(row.rpc),
+            row.version.as_deref().unwrap_or("-"),
+            row.shred_version,
+            now.saturating_sub(row.last_seen) / 1000,
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        dubstep_gossip::gossip_data::{contact_info::ContactInfo, gossip_data::GossipValue},
+        solana_sdk::{signature::Keypair, signer::Signer},
+    };
+
+    #[test]
+    fn test_node_rows() {
+        let keypair = Keypair::new();
+        let now = timestamp();
+        let gossip: SocketAddr = "10.0.0.1:8001".parse().unwrap();
+        let contact_info = ContactInfo::new(keypair.pubkey(), now, 42, gossip);
+        let mut table = GossipTable::default();
+        table
+            .insert(
+                GossipValue::new_signed(GossipData::ContactInfo(contact_info), &keypair),
+                now - 3_000,
+            )
+            .unwrap();
+
+        let rows = node_rows(&table);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].gossip, Some(gossip));
+        assert_eq!(rows[0].tpu, None);
+        assert_eq!(rows[0].shred_version, 42);
+
+        let out = render(&rows, now);
+        assert!(out.starts_with("1 nodes\n"));
+        assert!(out.contains(&keypair.pubkey().to_string()));
+        assert!(out.contains("10.0.0.1:8001"));
+        assert!(out.trim_end().ends_with("3s"));
+    }
+}
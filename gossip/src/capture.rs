@@ -0,0 +1,360 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Capture files start with the magic and the local address of the recording
+// connection. Every datagram follows as a record, integers little-endian:
+//
+//   timestamp_us: u64   microseconds since the epoch
+//   direction:    u8    0 inbound, 1 outbound
+//   address:            peer, as family (4 or 6: u8), ip bytes, port: u16
+//   len:          u32
+//   data:         [u8; len]
+const MAGIC: &[u8; 8] = b"DUBCAP01";
+
+// Largest UDP payload, anything longer is a corrupt record.
+const MAX_RECORD_LEN: usize = 65_507;
+const CAPTURE_QUEUE_CAPACITY: usize = 1_024;
+// Warns on the first dropped batch and every this many after it.
+const DROPPED_WARN_INTERVAL: u64 = 1_000;
+
+type CaptureBatch = (u64, Direction, Vec<(Vec<u8>, SocketAddr)>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaptureRecord {
+    pub timestamp_us: u64,
+    pub direction: Direction,
+    pub addr: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+fn timestamp_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_micros() as u64)
+}
+
+fn write_addr(writer: &mut impl Write, addr: &SocketAddr) -> io::Result<()> {
+    match addr.ip() {
+        IpAddr::V4(ip) => {
+            writer.write_all(&[4])?;
+            writer.write_all(&ip.octets())?;
+        }
+        IpAddr::V6(ip) => {
+            writer.write_all(&[6])?;
+            writer.write_all(&ip.octets())?;
+        }
+    }
+    writer.write_all(&addr.port().to_le_bytes())
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_addr(reader: &mut impl Read) -> io::Result<SocketAddr> {
+    let ip = match read_array::<1>(reader)? {
+        [4] => IpAddr::V4(Ipv4Addr::from(read_array::<4>(reader)?)),
+        [6] => IpAddr::V6(Ipv6Addr::from(read_array::<16>(reader)?)),
+        [family] => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown address family {}", family),
+            ))
+        }
+    };
+    let port = u16::from_le_bytes(read_array(reader)?);
+    Ok(SocketAddr::new(ip, port))
+}
+
+// Appends datagrams to a capture file. Each batch is flushed as a whole so
+// the file stays readable if the process is killed.
+pub struct CaptureWriter {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl CaptureWriter {
+    pub fn create(path: impl AsRef<Path>, local_addr: SocketAddr) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        write_addr(&mut writer, &local_addr)?;
+        writer.flush()?;
+        Ok(Self {
+            writer: Mutex::new(writer),
+        })
+    }
+
+    pub fn record<'a>(
+        &self,
+        direction: Direction,
+        packets: impl IntoIterator<Item = (&'a [u8], SocketAddr)>,
+    ) -> io::Result<()> {
+        self.record_at(timestamp_us(), direction, packets)
+    }
+
+    // Moves the writer to its own thread fed by the returned sender, so
+    // recording never blocks the caller on disk.
+    pub fn spawn(self) -> CaptureSender {
+        let (sender, receiver) = mpsc::sync_channel(CAPTURE_QUEUE_CAPACITY);
+        let thread = thread::Builder::new()
+            .name("gossip-capture".to_string())
+            .spawn(move || self.write_loop(receiver))
+            .expect("failed to spawn capture writer");
+        CaptureSender {
+            sender: Mutex::new(Some(sender)),
+            thread: Mutex::new(Some(thread)),
+            dropped: AtomicU64::default(),
+        }
+    }
+
+    fn write_loop(self, receiver: Receiver<CaptureBatch>) {
+        for (timestamp_us, direction, packets) in receiver {
+            let packets = packets.iter().map(|(data, addr)| (data.as_slice(), *addr));
+            if let Err(e) = self.record_at(timestamp_us, direction, packets) {
+                log::warn!("Failed to write capture {:?}", e);
+            }
+        }
+    }
+
+    // Like `record` with a given time, for converting other captures.
+    pub fn record_at<'a>(
+        &self,
+        timestamp_us: u64,
+        direction: Direction,
+        packets: impl IntoIterator<Item = (&'a [u8], SocketAddr)>,
+    ) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        for (data, addr) in packets {
+            writer.write_all(&timestamp_us.to_le_bytes())?;
+            writer.write_all(&[direction as u8])?;
+            write_addr(&mut *writer, &addr)?;
+            writer.write_all(&(data.len() as u32).to_le_bytes())?;
+            writer.write_all(data)?;
+        }
+        writer.flush()
+    }
+}
+
+pub struct CaptureSender {
+    // Taken on close, packets recorded after that are ignored.
+    sender: Mutex<Option<SyncSender<CaptureBatch>>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    dropped: AtomicU64,
+}
+
+impl CaptureSender {
+    // Queues the packets, timestamped now. Dropped if the writer fell behind.
+    pub fn record<'a>(
+        &self,
+        direction: Direction,
+        packets: impl IntoIterator<Item = (&'a [u8], SocketAddr)>,
+    ) {
+        let sender = self.sender.lock().unwrap();
+        let Some(sender) = sender.as_ref() else {
+            return;
+        };
+        let packets = packets
+            .into_iter()
+            .map(|(data, addr)| (data.to_vec(), addr))
+            .collect();
+        match sender.try_send((timestamp_us(), direction, packets)) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed);
+                if dropped.is_multiple_of(DROPPED_WARN_INTERVAL) {
+                    log::warn!(
+                        "Capture writer is behind, {} batches dropped so far",
+                        dropped + 1
+                    );
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                log::warn!("Capture writer stopped, dropping packets");
+            }
+        }
+    }
+
+    // Stops recording and waits until every queued batch is on disk.
+    pub fn close(&self) {
+        drop(self.sender.lock().unwrap().take());
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            if thread.join().is_err() {
+                log::warn!("Capture writer panicked");
+            }
+        }
+    }
+
+    // Batches not captured because the writer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+pub struct CaptureReader<R> {
+    reader: R,
+    local_addr: SocketAddr,
+}
+
+impl CaptureReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        if &read_array::<8>(&mut reader)? != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a capture file",
+            ));
+        }
+        let local_addr = read_addr(&mut reader)?;
+        Ok(Self { reader, local_addr })
+    }
+
+    // Address of the connection that recorded the capture.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    // The next record, or `None` at the end of the file. A record cut short
+    // by a crash while writing is an error.
+    pub fn next_record(&mut self) -> io::Result<Option<CaptureRecord>> {
+        let mut timestamp = [0u8; 8];
+        match self.reader.read(&mut timestamp[..1])? {
+            0 => return Ok(None),
+            _ => self.reader.read_exact(&mut timestamp[1..])?,
+        }
+        let direction = match read_array::<1>(&mut self.reader)? {
+            [0] => Direction::Inbound,
+            [1] => Direction::Outbound,
+            [direction] => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown direction {}", direction),
+                ))
+            }
+        };
+        let addr = read_addr(&mut self.reader)?;
+        let len = u32::from_le_bytes(read_array(&mut self.reader)?) as usize;
+        if len > MAX_RECORD_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Record of {} bytes is longer than a datagram", len),
+            ));
+        }
+        let mut data = vec![0u8; len];
+        self.reader.read_exact(&mut data)?;
+        Ok(Some(CaptureRecord {
+            timestamp_us: u64::from_le_bytes(timestamp),
+            direction,
+            addr,
+            data,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = io::Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_roundtrip() {
+        let path = std::env::temp_dir().join(format!("capture-{}.cap", std::process::id()));
+        let local: SocketAddr = "10.0.0.1:8001".parse().unwrap();
+        let peer_v4: SocketAddr = "1.2.3.4:8001".parse().unwrap();
+        let peer_v6: SocketAddr = "[2001:db8::1]:8001".parse().unwrap();
+        let writer = CaptureWriter::create(&path, local).unwrap();
+        writer
+            .record(
+                Direction::Inbound,
+                [(&[1u8, 2][..], peer_v4), (&[3u8][..], peer_v6)],
+            )
+            .unwrap();
+        writer
+            .record(Direction::Outbound, [(&[][..], peer_v4)])
+            .unwrap();
+        drop(writer);
+
+        let reader = CaptureReader::open(&path).unwrap();
+        assert_eq!(reader.local_addr(), local);
+        let records: Vec<CaptureRecord> = reader.collect::<io::Result<_>>().unwrap();
+        let records: Vec<(Direction, SocketAddr, Vec<u8>)> = records
+            .into_iter()
+            .map(|record| (record.direction, record.addr, record.data))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (Direction::Inbound, peer_v4, vec![1, 2]),
+                (Direction::Inbound, peer_v6, vec![3]),
+                (Direction::Outbound, peer_v4, vec![]),
+            ]
+        );
+
+        // A record cut short is an error, not the end of the capture.
+        let bytes = std::fs::read(&path).unwrap();
+        let mut reader = CaptureReader::new(&bytes[..bytes.len() - 2]).unwrap();
+        assert!(reader.next_record().unwrap().is_some());
+        assert!(reader.next_record().unwrap().is_some());
+        assert!(reader.next_record().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_capture_rejects_huge_record() {
+        let mut bytes = MAGIC.to_vec();
+        write_addr(&mut bytes, &"10.0.0.1:8001".parse().unwrap()).unwrap();
+        bytes.extend(1u64.to_le_bytes());
+        bytes.push(0);
+        write_addr(&mut bytes, &"1.2.3.4:8001".parse().unwrap()).unwrap();
+        bytes.extend(u32::MAX.to_le_bytes());
+
+        let mut reader = CaptureReader::new(&bytes[..]).unwrap();
+        let err = reader.next_record().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_capture_sender_flushes_on_close() {
+        let path = std::env::temp_dir().join(format!("capture-close-{}.cap", std::process::id()));
+        let local: SocketAddr = "10.0.0.1:8001".parse().unwrap();
+        let sender = CaptureWriter::create(&path, local).unwrap().spawn();
+        for _ in 0..100 {
+            sender.record(Direction::Inbound, [(&[1u8, 2][..], local)]);
+        }
+        sender.close();
+        sender.record(Direction::Inbound, [(&[3u8][..], local)]);
+
+        let reader = CaptureReader::open(&path).unwrap();
+        assert_eq!(reader.map(Result::unwrap).count(), 100);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,7 +1,19 @@
 use {
-    std::{net::SocketAddr, sync::Arc},
+    crate::{
+        capture::{CaptureSender, CaptureWriter, Direction},
+        metrics::TrafficStats,
+        transport::{udp::UdpTransport, Transport},
+    },
+    solana_sdk::packet::PACKET_DATA_SIZE,
+    std::{
+        io,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, OnceLock,
+        },
+    },
     tokio::{
-        net::UdpSocket,
         sync::{
             mpsc::{self, Receiver, Sender},
             Mutex,
@@ -10,60 +22,317 @@ use {
     },
 };
 
+pub type Packet = (Vec<u8>, SocketAddr);
+pub type PacketBatch = Vec<Packet>;
+
+const PACKET_BATCH_SIZE: usize = 64;
+const BATCH_CHANNEL_CAPACITY: usize = 1_024;
+const PACKET_CHANNEL_CAPACITY: usize = 8_192;
+
+// One extra byte so datagrams over the protocol limit can be told apart from
+// ones that exactly fill a packet.
+const RECV_BUFFER_SIZE: usize = PACKET_DATA_SIZE + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropReason {
+    Truncated,
+    RecvFailed,
+    SendFailed,
+    ChannelClosed,
+    DecodeFailed,
+}
+
+impl DropReason {
+    pub const ALL: [DropReason; 5] = [
+        DropReason::Truncated,
+        DropReason::RecvFailed,
+        DropReason::SendFailed,
+        DropReason::ChannelClosed,
+        DropReason::DecodeFailed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DropReason::Truncated => "truncated",
+            DropReason::RecvFailed => "recv_failed",
+            DropReason::SendFailed => "send_failed",
+            DropReason::ChannelClosed => "channel_closed",
+            DropReason::DecodeFailed => "decode_failed",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DropStats {
+    counts: [AtomicU64; DropReason::ALL.len()],
+}
+
+impl DropStats {
+    pub fn record(&self, reason: DropReason) {
+        self.counts[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, reason: DropReason) -> u64 {
+        self.counts[reason as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> u64 {
+        DropReason::ALL.iter().map(|reason| self.get(*reason)).sum()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConnectionConfig {
+    // Use recvmmsg/sendmmsg, only available on Linux.
+    pub batch_io: bool,
+    // SO_RCVBUF in bytes, the OS default when unset.
+    pub recv_buffer_size: Option<usize>,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            batch_io: cfg!(target_os = "linux"),
+            recv_buffer_size: None,
+        }
+    }
+}
+
 pub struct Connection {
-    pub udp_socket: Arc<UdpSocket>,
-    pub rx_in: Arc<Mutex<Receiver<(Vec<u8>, SocketAddr)>>>,
-    pub tx_out: Sender<(Vec<u8>, SocketAddr)>,
-    tx_in: Sender<(Vec<u8>, SocketAddr)>,
-    rx_out: Arc<Mutex<Receiver<(Vec<u8>, SocketAddr)>>>,
+    transport: Arc<dyn Transport>,
+    pub rx_in: Arc<Mutex<Receiver<PacketBatch>>>,
+    pub tx_out: Sender<Packet>,
+    tx_in: Sender<PacketBatch>,
+    rx_out: Arc<Mutex<Receiver<Packet>>>,
+    drops: Arc<DropStats>,
+    traffic_in: TrafficStats,
+    traffic_out: TrafficStats,
+    capture: OnceLock<CaptureSender>,
 }
 
 impl Connection {
     pub async fn new(udp_socket: &str) -> tokio::io::Result<Arc<Self>> {
-        let udp_socket = Arc::new(UdpSocket::bind(udp_socket).await?);
-        let (tx_in, rx_in) = mpsc::channel(100);
-        let (tx_out, rx_out) = mpsc::channel(100);
+        Self::new_with_config(udp_socket, ConnectionConfig::default()).await
+    }
+
+    pub async fn new_with_config(
+        udp_socket: &str,
+        config: ConnectionConfig,
+    ) -> tokio::io::Result<Arc<Self>> {
+        let transport = UdpTransport::bind(udp_socket, &config).await?;
+        Ok(Self::with_transport(Arc::new(transport)))
+    }
 
-        let connection = Arc::new(Self {
-            udp_socket,
+    pub fn with_transport(transport: Arc<dyn Transport>) -> Arc<Self> {
+        let (tx_in, rx_in) = mpsc::channel(BATCH_CHANNEL_CAPACITY);
+        let (tx_out, rx_out) = mpsc::channel(PACKET_CHANNEL_CAPACITY);
+
+        Arc::new(Self {
+            transport,
             rx_in: Arc::new(Mutex::new(rx_in)),
             tx_out,
             tx_in,
             rx_out: Arc::new(Mutex::new(rx_out)),
-        });
+            drops: Arc::default(),
+            traffic_in: TrafficStats::default(),
+            traffic_out: TrafficStats::default(),
+            capture: OnceLock::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.transport.local_addr()
+    }
+
+    pub fn drops(&self) -> &DropStats {
+        &self.drops
+    }
+
+    pub fn traffic_in(&self) -> &TrafficStats {
+        &self.traffic_in
+    }
+
+    pub fn traffic_out(&self) -> &TrafficStats {
+        &self.traffic_out
+    }
+
+    // Records every datagram received or sent from now on, written from a
+    // separate thread. Only one capture per connection, later ones are refused.
+    pub fn set_capture(&self, capture: CaptureWriter) -> Result<(), CaptureWriter> {
+        let mut capture = Some(capture);
+        self.capture
+            .get_or_init(|| capture.take().expect("initialized once").spawn());
+        match capture {
+            None => Ok(()),
+            Some(capture) => Err(capture),
+        }
+    }
+
+    // Stops the capture, if any, once everything recorded so far is written.
+    pub fn close_capture(&self) {
+        if let Some(capture) = self.capture.get() {
+            capture.close();
+        }
+    }
 
-        Ok(connection)
+    fn capture<'a>(
+        &self,
+        direction: Direction,
+        packets: impl IntoIterator<Item = (&'a [u8], SocketAddr)>,
+    ) {
+        if let Some(capture) = self.capture.get() {
+            capture.record(direction, packets);
+        }
     }
 
     pub fn start_receiving(self: &Arc<Self>) {
-        let udp_socket = Arc::clone(&self.udp_socket);
-        let tx_channel = self.tx_in.clone();
+        let connection = Arc::clone(self);
 
         task::spawn(async move {
-            let mut buf = vec![0u8; 1260];
+            let mut bufs = vec![vec![0u8; RECV_BUFFER_SIZE]; PACKET_BATCH_SIZE];
             loop {
-                if let Ok((size, src)) = udp_socket.recv_from(&mut buf).await {
-                    let msg = buf[..size].to_vec();
-                    if tx_channel.send((msg, src)).await.is_err() {
-                        eprintln!("Receiver dropped");
-                        break;
+                let received = match connection.transport.recv_batch(&mut bufs).await {
+                    Ok(received) => received,
+                    Err(e) => {
+                        connection.drops.record(DropReason::RecvFailed);
+                        log::warn!("Failed to receive data {:?}", e);
+                        continue;
                     }
+                };
+                connection.capture(
+                    Direction::Inbound,
+                    received
+                        .iter()
+                        .zip(&bufs)
+                        .map(|((size, src), buf)| (&buf[..(*size).min(buf.len())], *src)),
+                );
+                let batch: PacketBatch = received
+                    .into_iter()
+                    .zip(&bufs)
+                    .filter_map(|((size, src), buf)| {
+                        if size > PACKET_DATA_SIZE {
+                            connection.drops.record(DropReason::Truncated);
+                            log::debug!("Dropped oversized datagram from:{:?}", src);
+                            return None;
+                        }
+                        connection.traffic_in.record(&buf[..size]);
+                        Some((buf[..size].to_vec(), src))
+                    })
+                    .collect();
+                if batch.is_empty() {
+                    continue;
+                }
+                if connection.tx_in.send(batch).await.is_err() {
+                    connection.drops.record(DropReason::ChannelClosed);
+                    log::warn!("Receiver dropped");
+                    break;
                 }
             }
         });
     }
 
     pub fn start_sending(self: &Arc<Self>) {
-        let udp_socket = Arc::clone(&self.udp_socket);
-        let rx_channel = Arc::clone(&self.rx_out);
+        let connection = Arc::clone(self);
 
         task::spawn(async move {
-            let mut rx_channel = rx_channel.lock().await;
-            while let Some((msg, addr)) = rx_channel.recv().await {
-                if let Err(e) = udp_socket.send_to(&msg, &addr).await {
-                    eprintln!("CONNECTION: Failed to send data to:{:?} {:?}", addr, e);
+            let mut rx_channel = connection.rx_out.lock().await;
+            let mut packets = Vec::with_capacity(PACKET_BATCH_SIZE);
+            while rx_channel.recv_many(&mut packets, PACKET_BATCH_SIZE).await > 0 {
+                for (data, _) in &packets {
+                    connection.traffic_out.record(data);
+                }
+                connection.capture(
+                    Direction::Outbound,
+                    packets.iter().map(|(data, addr)| (data.as_slice(), *addr)),
+                );
+                for (addr, e) in connection.transport.send_batch(&packets).await {
+                    connection.drops.record(DropReason::SendFailed);
+                    log::warn!("Failed to send data to:{:?} {:?}", addr, e);
                 }
+                packets.clear();
             }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::time::Duration,
+        tokio::{net::UdpSocket, time::timeout},
+    };
+
+    #[tokio::test]
+    async fn test_drop_oversized_datagrams() {
+        let connection = Connection::new("127.0.0.1:0").await.unwrap();
+        connection.start_receiving();
+        let addr = connection.local_addr().unwrap();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        socket
+            .send_to(&[1u8; PACKET_DATA_SIZE + 100], addr)
+            .await
+            .unwrap();
+        socket
+            .send_to(&[2u8; PACKET_DATA_SIZE], addr)
+            .await
+            .unwrap();
+
+        let batch = timeout(Duration::from_secs(5), async {
+            connection.rx_in.lock().await.recv().await
+        })
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].0, vec![2u8; PACKET_DATA_SIZE]);
+        assert_eq!(connection.drops().get(DropReason::Truncated), 1);
+        assert_eq!(connection.drops().total(), 1);
+    }
+
+    async fn exchange_packets(config: ConnectionConfig) {
+        let sender = Connection::new_with_config("127.0.0.1:0", config.clone())
+            .await
+            .unwrap();
+        let receiver = Connection::new_with_config("127.0.0.1:0", config)
+            .await
+            .unwrap();
+        sender.start_sending();
+        receiver.start_receiving();
+        let addr = receiver.local_addr().unwrap();
+
+        for i in 0..200u8 {
+            sender.tx_out.send((vec![i; 100], addr)).await.unwrap();
+        }
+
+        let mut received = vec![];
+        timeout(Duration::from_secs(5), async {
+            let mut rx_in = receiver.rx_in.lock().await;
+            while received.len() < 200 {
+                received.extend(rx_in.recv().await.unwrap());
+            }
+        })
+        .await
+        .unwrap();
+        let received: Vec<u8> = received.into_iter().map(|(msg, _)| msg[0]).collect();
+        assert_eq!(received, (0..200u8).collect::<Vec<_>>());
+        assert_eq!(sender.traffic_out().packets(), 200);
+        assert_eq!(receiver.traffic_in().packets(), 200);
+        assert_eq!(receiver.traffic_in().bytes(), sender.traffic_out().bytes());
+    }
+
+    #[tokio::test]
+    async fn test_exchange_packets() {
+        exchange_packets(ConnectionConfig {
+            batch_io: false,
+            recv_buffer_size: None,
+        })
+        .await;
+        exchange_packets(ConnectionConfig {
+            batch_io: true,
+            recv_buffer_size: Some(1 << 20),
+        })
+        .await;
+    }
+}
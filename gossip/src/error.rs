@@ -0,0 +1,171 @@
+use {
+    crate::{
+        gossip_data::sanitize::SanitizeErrors, ping_pong::PingPongErrors,
+        pull_request::PullRequestErrors, push_message::PushErrors,
+    },
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        net::SocketAddr,
+        sync::atomic::{AtomicU64, Ordering},
+    },
+    thiserror::Error,
+};
+
+// Every reason the node ignores traffic or fails to send it.
+#[derive(Debug, Error)]
+pub enum GossipError {
+    #[error("Failed to decode message from:{from:?} {reason}")]
+    Decode { from: SocketAddr, reason: String },
+    #[error("Failed to sanitize message from:{from:?} {error}")]
+    Sanitize {
+        from: SocketAddr,
+        error: SanitizeErrors,
+    },
+    #[error("Invalid signature from:{from:?} origin:{origin}")]
+    Signature { from: SocketAddr, origin: Pubkey },
+    #[error("Rate limited {0:?}")]
+    RateLimited(SocketAddr),
+    #[error("Shred version {actual} from origin:{origin} does not match {expected}")]
+    ShredVersion {
+        origin: Pubkey,
+        expected: u16,
+        actual: u16,
+    },
+    #[error("Prune from:{from:?} is meant for {destination}")]
+    PruneDestination {
+        from: SocketAddr,
+        destination: Pubkey,
+    },
+    #[error("Prune from:{from:?} signed at {wallclock} has expired")]
+    PruneExpired { from: SocketAddr, wallclock: u64 },
+    #[error("Dropped by a handler from:{0:?}")]
+    HandlerDropped(SocketAddr),
+    #[error("Failed to send to:{0:?}")]
+    Send(SocketAddr),
+    #[error(transparent)]
+    PingPong(#[from] PingPongErrors),
+    #[error(transparent)]
+    PullRequest(#[from] PullRequestErrors),
+    #[error(transparent)]
+    Push(#[from] PushErrors),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GossipErrorKind {
+    Decode,
+    Sanitize,
+    Signature,
+    RateLimited,
+    ShredVersion,
+    PruneDestination,
+    PruneExpired,
+    HandlerDropped,
+    Send,
+    PingPong,
+    PullRequest,
+    Push,
+}
+
+impl GossipErrorKind {
+    pub const ALL: [GossipErrorKind; 12] = [
+        GossipErrorKind::Decode,
+        GossipErrorKind::Sanitize,
+        GossipErrorKind::Signature,
+        GossipErrorKind::RateLimited,
+        GossipErrorKind::ShredVersion,
+        GossipErrorKind::PruneDestination,
+        GossipErrorKind::PruneExpired,
+        GossipErrorKind::HandlerDropped,
+        GossipErrorKind::Send,
+        GossipErrorKind::PingPong,
+        GossipErrorKind::PullRequest,
+        GossipErrorKind::Push,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GossipErrorKind::Decode => "decode",
+            GossipErrorKind::Sanitize => "sanitize",
+            GossipErrorKind::Signature => "signature",
+            GossipErrorKind::RateLimited => "rate_limited",
+            GossipErrorKind::ShredVersion => "shred_version",
+            GossipErrorKind::PruneDestination => "prune_destination",
+            GossipErrorKind::PruneExpired => "prune_expired",
+            GossipErrorKind::HandlerDropped => "handler_dropped",
+            GossipErrorKind::Send => "send",
+            GossipErrorKind::PingPong => "ping_pong",
+            GossipErrorKind::PullRequest => "pull_request",
+            GossipErrorKind::Push => "push",
+        }
+    }
+}
+
+impl GossipError {
+    pub fn kind(&self) -> GossipErrorKind {
+        match self {
+            GossipError::Decode { .. } => GossipErrorKind::Decode,
+            GossipError::Sanitize { .. } => GossipErrorKind::Sanitize,
+            GossipError::Signature { .. } => GossipErrorKind::Signature,
+            GossipError::RateLimited(_) => GossipErrorKind::RateLimited,
+            GossipError::ShredVersion { .. } => GossipErrorKind::ShredVersion,
+            GossipError::PruneDestination { .. } => GossipErrorKind::PruneDestination,
+            GossipError::PruneExpired { .. } => GossipErrorKind::PruneExpired,
+            GossipError::HandlerDropped(_) => GossipErrorKind::HandlerDropped,
+            GossipError::Send(_) => GossipErrorKind::Send,
+            GossipError::PingPong(_) => GossipErrorKind::PingPong,
+            GossipError::PullRequest(_) => GossipErrorKind::PullRequest,
+            GossipError::Push(_) => GossipErrorKind::Push,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ErrorStats {
+    counts: [AtomicU64; GossipErrorKind::ALL.len()],
+}
+
+impl ErrorStats {
+    // Counts the error and logs it. Inbound drops are routine on mainnet so
+    // they only show at debug level, failures on our side are warnings.
+    pub fn report(&self, error: &GossipError) {
+        let kind = error.kind();
+        self.counts[kind as usize].fetch_add(1, Ordering::Relaxed);
+        match kind {
+            GossipErrorKind::Send
+            | GossipErrorKind::PingPong
+            | GossipErrorKind::PullRequest
+            | GossipErrorKind::Push => log::warn!("{}", error),
+            _ => log::debug!("{}", error),
+        }
+    }
+
+    pub fn get(&self, kind: GossipErrorKind) -> u64 {
+        self.counts[kind as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> u64 {
+        GossipErrorKind::ALL
+            .iter()
+            .map(|kind| self.get(*kind))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_stats_count_per_kind() {
+        let stats = ErrorStats::default();
+        let from = SocketAddr::from(([127, 0, 0, 1], 8001));
+        stats.report(&GossipError::RateLimited(from));
+        stats.report(&GossipError::RateLimited(from));
+        stats.report(&PushErrors::SerializeFailed.into());
+
+        assert_eq!(stats.get(GossipErrorKind::RateLimited), 2);
+        assert_eq!(stats.get(GossipErrorKind::Push), 1);
+        assert_eq!(stats.get(GossipErrorKind::Decode), 0);
+        assert_eq!(stats.total(), 3);
+    }
+}
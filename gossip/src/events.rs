@@ -0,0 +1,276 @@
+use {
+    crate::gossip_data::{
+        contact_info::ContactInfo,
+        gossip_data::{DuplicateShred, GossipData, GossipValue, SnapshotHashes, Vote},
+    },
+    solana_sdk::pubkey::Pubkey,
+    std::collections::HashSet,
+    thiserror::Error,
+    tokio::sync::broadcast::{self, error::RecvError},
+};
+
+pub const EVENT_CHANNEL_CAPACITY: usize = 4_096;
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum GossipEvent {
+    NodeJoined(ContactInfo),
+    NodeLeft(Pubkey),
+    ContactInfoChanged {
+        old: ContactInfo,
+        new: ContactInfo,
+    },
+    Vote {
+        from: Pubkey,
+        index: u8,
+        vote: Vote,
+    },
+    SnapshotHashes(SnapshotHashes),
+    DuplicateShred {
+        from: Pubkey,
+        index: u16,
+        shred: DuplicateShred,
+    },
+    VersionChanged {
+        from: Pubkey,
+        previous: Option<String>,
+        version: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GossipEventKind {
+    NodeJoined,
+    NodeLeft,
+    ContactInfoChanged,
+    Vote,
+    SnapshotHashes,
+    DuplicateShred,
+    VersionChanged,
+}
+
+impl GossipEventKind {
+    pub const ALL: [GossipEventKind; 7] = [
+        GossipEventKind::NodeJoined,
+        GossipEventKind::NodeLeft,
+        GossipEventKind::ContactInfoChanged,
+        GossipEventKind::Vote,
+        GossipEventKind::SnapshotHashes,
+        GossipEventKind::DuplicateShred,
+        GossipEventKind::VersionChanged,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GossipEventKind::NodeJoined => "node_joined",
+            GossipEventKind::NodeLeft => "node_left",
+            GossipEventKind::ContactInfoChanged => "contact_info_changed",
+            GossipEventKind::Vote => "vote",
+            GossipEventKind::SnapshotHashes => "snapshot_hashes",
+            GossipEventKind::DuplicateShred => "duplicate_shred",
+            GossipEventKind::VersionChanged => "version_changed",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == kind)
+    }
+}
+
+impl GossipEvent {
+    pub fn kind(&self) -> GossipEventKind {
+        match self {
+            GossipEvent::NodeJoined(_) => GossipEventKind::NodeJoined,
+            GossipEvent::NodeLeft(_) => GossipEventKind::NodeLeft,
+            GossipEvent::ContactInfoChanged { .. } => GossipEventKind::ContactInfoChanged,
+            GossipEvent::Vote { .. } => GossipEventKind::Vote,
+            GossipEvent::SnapshotHashes(_) => GossipEventKind::SnapshotHashes,
+            GossipEvent::DuplicateShred { .. } => GossipEventKind::DuplicateShred,
+            GossipEvent::VersionChanged { .. } => GossipEventKind::VersionChanged,
+        }
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        match self {
+            GossipEvent::NodeJoined(contact_info) => *contact_info.pubkey(),
+            GossipEvent::NodeLeft(pubkey) => *pubkey,
+            GossipEvent::ContactInfoChanged { new, .. } => *new.pubkey(),
+            GossipEvent::Vote { from, .. } => *from,
+            GossipEvent::SnapshotHashes(hashes) => hashes.from,
+            GossipEvent::DuplicateShred { from, .. } => *from,
+            GossipEvent::VersionChanged { from, .. } => *from,
+        }
+    }
+}
+
+// Events a value produces when it replaces `old` in the table.
+pub fn events_for(value: &GossipValue, old: Option<&GossipValue>) -> Vec<GossipEvent> {
+    let from = value.pubkey();
+    match (&value.data, old.map(|old| &old.data)) {
+        (GossipData::ContactInfo(new), Some(GossipData::ContactInfo(old))) => {
+            let mut events = vec![];
+            if new.version() != old.version() {
+                events.push(GossipEvent::VersionChanged {
+                    from,
+                    previous: Some(old.version().to_string()),
+                    version: new.version().to_string(),
+                });
+            }
+            // Wallclock bumps alone are just refreshes.
+            let mut refreshed = old.clone();
+            refreshed.set_wallclock(new.wallclock());
+            if refreshed != *new {
+                events.push(GossipEvent::ContactInfoChanged {
+                    old: old.clone(),
+                    new: new.clone(),
+                });
+            }
+            events
+        }
+        (GossipData::ContactInfo(new), _) => vec![GossipEvent::NodeJoined(new.clone())],
+        (GossipData::Vote(index, vote), _) => vec![GossipEvent::Vote {
+            from,
+            index: *index,
+            vote: vote.clone(),
+        }],
+        (GossipData::SnapshotHashes(hashes), _) => {
+            vec![GossipEvent::SnapshotHashes(hashes.clone())]
+        }
+        (GossipData::DuplicateShred(index, shred), _) => vec![GossipEvent::DuplicateShred {
+            from,
+            index: *index,
+            shred: shred.clone(),
+        }],
+        (GossipData::Version(new), old) => {
+            let previous = match old {
+                Some(GossipData::Version(old)) if old.version == new.version => return vec![],
+                Some(GossipData::Version(old)) => Some(old.version.to_string()),
+                _ => None,
+            };
+            vec![GossipEvent::VersionChanged {
+                from,
+                previous,
+                version: new.version.to_string(),
+            }]
+        }
+        _ => vec![],
+    }
+}
+
+// Matches every event unless narrowed down to some kinds or pubkeys.
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    kinds: Option<HashSet<GossipEventKind>>,
+    pubkeys: Option<HashSet<Pubkey>>,
+}
+
+impl EventFilter {
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = GossipEventKind>) -> Self {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
+
+    pub fn pubkeys(mut self, pubkeys: impl IntoIterator<Item = Pubkey>) -> Self {
+        self.pubkeys = Some(pubkeys.into_iter().collect());
+        self
+    }
+
+    pub fn matches(&self, event: &GossipEvent) -> bool {
+        self.kinds
+            .as_ref()
+            .is_none_or(|kinds| kinds.contains(&event.kind()))
+            && self
+                .pubkeys
+                .as_ref()
+                .is_none_or(|pubkeys| pubkeys.contains(&event.pubkey()))
+    }
+}
+
+pub struct EventReceiver {
+    receiver: broadcast::Receiver<GossipEvent>,
+    filter: EventFilter,
+}
+
+impl EventReceiver {
+    pub fn new(receiver: broadcast::Receiver<GossipEvent>, filter: EventFilter) -> Self {
+        Self { receiver, filter }
+    }
+
+    // A slow subscriber gets `Lagged` with the number of events it missed,
+    // then picks up again from the oldest event still buffered.
+    pub async fn recv(&mut self) -> Result<GossipEvent, EventErrors> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if self.filter.matches(&event) => return Ok(event),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => return Err(EventErrors::Lagged(missed)),
+                Err(RecvError::Closed) => return Err(EventErrors::Closed),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum EventErrors {
+    #[error("Subscriber lagged behind and missed {0} events")]
+    Lagged(u64),
+    #[error("Event stream closed")]
+    Closed,
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{signature::Keypair, signer::Signer, timing::timestamp},
+    };
+
+    fn sign(keypair: &Keypair, contact_info: &ContactInfo, wallclock: u64) -> GossipValue {
+        let mut contact_info = contact_info.clone();
+        contact_info.set_wallclock(wallclock);
+        GossipValue::new_signed(GossipData::ContactInfo(contact_info), keypair)
+    }
+
+    #[test]
+    fn test_contact_info_events() {
+        let keypair = Keypair::new();
+        let now = timestamp();
+        let contact_info =
+            ContactInfo::new(keypair.pubkey(), now, 0, ([127, 0, 0, 1], 8001).into());
+        let first = sign(&keypair, &contact_info, now);
+        let refreshed = sign(&keypair, &contact_info, now + 1);
+        let moved = ContactInfo::new(keypair.pubkey(), now, 0, ([127, 0, 0, 1], 8002).into());
+        let moved = sign(&keypair, &moved, now + 2);
+
+        let events = events_for(&first, None);
+        assert!(matches!(events[..], [GossipEvent::NodeJoined(_)]));
+        assert!(events_for(&refreshed, Some(&first)).is_empty());
+        let events = events_for(&moved, Some(&refreshed));
+        assert!(matches!(
+            events[..],
+            [GossipEvent::ContactInfoChanged { .. }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_filter_and_lag() {
+        let (sender, _) = broadcast::channel(2);
+        let pubkey = Pubkey::new_unique();
+        let filter = EventFilter::default()
+            .kinds([GossipEventKind::NodeLeft])
+            .pubkeys([pubkey]);
+        let mut receiver = EventReceiver::new(sender.subscribe(), filter);
+
+        sender
+            .send(GossipEvent::NodeLeft(Pubkey::new_unique()))
+            .unwrap();
+        sender.send(GossipEvent::NodeLeft(pubkey)).unwrap();
+        assert_eq!(receiver.recv().await.unwrap().pubkey(), pubkey);
+
+        for _ in 0..3 {
+            sender.send(GossipEvent::NodeLeft(pubkey)).unwrap();
+        }
+        assert_eq!(receiver.recv().await.unwrap_err(), EventErrors::Lagged(1));
+        assert_eq!(receiver.recv().await.unwrap().pubkey(), pubkey);
+    }
+}
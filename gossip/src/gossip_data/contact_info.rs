@@ -1,13 +1,35 @@
 use {
+    super::sanitize::{sanitize_wallclock, Sanitize, SanitizeErrors},
     serde::{Deserialize, Serialize},
     solana_sdk::{pubkey::Pubkey, timing::timestamp},
     std::{
+        collections::HashSet,
         net::{IpAddr, Ipv4Addr, SocketAddr},
         time::{SystemTime, UNIX_EPOCH},
     },
 };
 
 const SOCKET_CACHE_SIZE: usize = 12;
+const SOCKET_TAG_GOSSIP: u8 = 0;
+const SOCKET_TAG_RPC: u8 = 2;
+const SOCKET_TAG_TPU: u8 = 5;
+const SOCKET_TAG_TPU_QUIC: u8 = 8;
+const SOCKET_TAG_NAMES: [&str; 14] = [
+    "gossip",
+    "repair",
+    "rpc",
+    "rpc_pubsub",
+    "serve_repair",
+    "tpu",
+    "tpu_forwards",
+    "tpu_forwards_quic",
+    "tpu_quic",
+    "tpu_vote",
+    "tvu",
+    "tvu_quic",
+    "serve_repair_quic",
+    "tpu_vote_quic",
+];
 const SOCKET_ADDR_UNSPECIFIED: SocketAddr =
     SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), /*port:*/ 0u16);
 
@@ -45,7 +67,7 @@ impl ContactInfo {
         let mut vec_socket = vec![];
 
         let socket_entry = SocketEntry {
-            key: 0,
+            key: SOCKET_TAG_GOSSIP,
             index: 0,
             offset: gossip.port(),
         };
@@ -58,7 +80,7 @@ impl ContactInfo {
             outset: get_ouset(),
             shred_version,
             version: solana_version::Version::default(),
-            addrs: Vec::<IpAddr>::default(),
+            addrs: vec![gossip.ip()],
             sockets: vec_socket,
             extensions: Vec::<Extension>::default(),
             cache: [SOCKET_ADDR_UNSPECIFIED; SOCKET_CACHE_SIZE],
@@ -69,11 +91,65 @@ impl ContactInfo {
         &self.pubkey
     }
 
+    pub fn wallclock(&self) -> u64 {
+        self.wallclock
+    }
+
+    pub fn set_wallclock(&mut self, wallclock: u64) {
+        self.wallclock = wallclock;
+    }
+
+    pub fn version(&self) -> &solana_version::Version {
+        &self.version
+    }
+
+    pub fn shred_version(&self) -> u16 {
+        self.shred_version
+    }
+
     pub fn sockets(&self) -> &Vec<SocketEntry> {
         &self.sockets
     }
 
-    pub fn default() -> Self {
+    pub fn gossip(&self) -> Option<SocketAddr> {
+        self.socket(SOCKET_TAG_GOSSIP)
+    }
+
+    pub fn rpc(&self) -> Option<SocketAddr> {
+        self.socket(SOCKET_TAG_RPC)
+    }
+
+    pub fn tpu(&self) -> Option<SocketAddr> {
+        self.socket(SOCKET_TAG_TPU)
+    }
+
+    pub fn tpu_quic(&self) -> Option<SocketAddr> {
+        self.socket(SOCKET_TAG_TPU_QUIC)
+    }
+
+    fn socket(&self, key: u8) -> Option<SocketAddr> {
+        self.socket_addrs()
+            .find(|(tag, _)| *tag == key)
+            .map(|(_, addr)| addr)
+    }
+
+    // Every advertised socket with its tag, stopping at the first entry that
+    // doesn't resolve.
+    pub fn socket_addrs(&self) -> impl Iterator<Item = (u8, SocketAddr)> + '_ {
+        self.sockets.iter().scan(0u16, |port, entry| {
+            *port = port.checked_add(entry.offset)?;
+            let addr = self.addrs.get(usize::from(entry.index))?;
+            Some((entry.key, SocketAddr::new(*addr, *port)))
+        })
+    }
+}
+
+pub fn socket_tag_name(tag: u8) -> Option<&'static str> {
+    SOCKET_TAG_NAMES.get(usize::from(tag)).copied()
+}
+
+impl Default for ContactInfo {
+    fn default() -> Self {
         Self {
             pubkey: Pubkey::new_unique(),
             wallclock: timestamp(),
@@ -87,3 +163,92 @@ impl ContactInfo {
         }
     }
 }
+
+impl Sanitize for ContactInfo {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)?;
+
+        let mut seen = HashSet::with_capacity(self.addrs.len());
+        for addr in &self.addrs {
+            if !seen.insert(addr) {
+                return Err(SanitizeErrors::DuplicateIpAddr(*addr));
+            }
+        }
+
+        let mut keys = HashSet::with_capacity(self.sockets.len());
+        let mut hits = vec![false; self.addrs.len()];
+        for entry in &self.sockets {
+            if !keys.insert(entry.key) {
+                return Err(SanitizeErrors::DuplicateSocket(entry.key));
+            }
+            match hits.get_mut(usize::from(entry.index)) {
+                Some(hit) => *hit = true,
+                None => return Err(SanitizeErrors::IpIndexOutOfBounds(entry.index)),
+            }
+        }
+        if !hits.into_iter().all(|hit| hit) {
+            return Err(SanitizeErrors::UnusedIpAddr);
+        }
+
+        self.sockets
+            .iter()
+            .try_fold(0u16, |port, entry| port.checked_add(entry.offset))
+            .ok_or(SanitizeErrors::PortOffsetsOverflow)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_contact_info() {
+        let gossip: SocketAddr = "127.0.0.1:8001".parse().unwrap();
+        let contact_info = ContactInfo::new(Pubkey::new_unique(), timestamp(), 0, gossip);
+        // The gossip socket indexes into `addrs`, which must hold its ip.
+        assert_eq!(contact_info.addrs, vec![gossip.ip()]);
+        assert_eq!(contact_info.sanitize(), Ok(()));
+
+        let mut unused_addr = contact_info.clone();
+        unused_addr.addrs.push("127.0.0.2".parse().unwrap());
+        assert_eq!(unused_addr.sanitize(), Err(SanitizeErrors::UnusedIpAddr));
+
+        let mut duplicate_addr = contact_info.clone();
+        duplicate_addr.addrs.push(gossip.ip());
+        assert_eq!(
+            duplicate_addr.sanitize(),
+            Err(SanitizeErrors::DuplicateIpAddr(gossip.ip()))
+        );
+
+        let mut duplicate_socket = contact_info.clone();
+        duplicate_socket.sockets.push(SocketEntry {
+            key: 0,
+            index: 0,
+            offset: 1,
+        });
+        assert_eq!(
+            duplicate_socket.sanitize(),
+            Err(SanitizeErrors::DuplicateSocket(0))
+        );
+
+        let mut bad_index = contact_info.clone();
+        bad_index.sockets[0].index = 1;
+        assert_eq!(
+            bad_index.sanitize(),
+            Err(SanitizeErrors::IpIndexOutOfBounds(1))
+        );
+
+        let mut overflow = contact_info;
+        overflow.sockets.push(SocketEntry {
+            key: 1,
+            index: 0,
+            offset: u16::MAX,
+        });
+        assert_eq!(
+            overflow.sanitize(),
+            Err(SanitizeErrors::PortOffsetsOverflow)
+        );
+    }
+}
@@ -1,10 +1,13 @@
 use {
+    super::sanitize::{Sanitize, SanitizeErrors},
     serde::{Deserialize, Serialize},
     solana_bloom::bloom::Bloom,
     solana_sdk::hash::Hash,
 };
 
 const MASK_BITS: u32 = 7427;
+pub const FALSE_RATE: f64 = 0.1f64;
+pub const KEYS: f64 = 8f64;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DataFilter {
@@ -13,34 +16,78 @@ pub struct DataFilter {
     mask_bits: u32,
 }
 
+fn compute_mask(seed: u64, mask_bits: u32) -> u64 {
+    assert!(seed <= 2u64.pow(mask_bits));
+    let seed: u64 = seed.checked_shl(64 - mask_bits).unwrap_or(0x0);
+    seed | (!0u64).checked_shr(mask_bits).unwrap_or(!0x0)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+fn mask_bits(num_items: f64, max_items: f64) -> u32 {
+    // for small ratios this can result in a negative number, ensure it returns 0 instead
+    ((num_items / max_items).log2().ceil()).max(0.0) as u32
+}
+
+fn max_items(max_bits: f64, false_rate: f64, num_keys: f64) -> f64 {
+    let m = max_bits;
+    let p = false_rate;
+    let k = num_keys;
+    (m / (-k / (1f64 - (p.ln() / k).exp()).ln())).ceil()
+}
+
+fn hash_as_u64(hash: &Hash) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash.as_ref()[..8]);
+    u64::from_le_bytes(bytes)
+}
+
 impl DataFilter {
     pub fn mask_bits(&self) -> u32 {
         self.mask_bits
     }
-}
 
-impl Default for DataFilter {
-    fn default() -> Self {
-        fn compute_mask(seed: u64, mask_bits: u32) -> u64 {
-            assert!(seed <= 2u64.pow(mask_bits));
-            let seed: u64 = seed.checked_shl(64 - mask_bits).unwrap_or(0x0);
-            seed | (!0u64).checked_shr(mask_bits).unwrap_or(!0x0)
-        }
+    pub fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    pub fn num_bits(&self) -> u64 {
+        self.filter.bits.len()
+    }
+
+    pub fn num_bits_set(&self) -> u64 {
+        (0..self.num_bits())
+            .filter(|i| self.filter.bits.get(*i))
+            .count() as u64
+    }
+
+    pub fn test_mask(&self, hash: &Hash) -> bool {
+        let ones = (!0u64).checked_shr(self.mask_bits).unwrap_or(0);
+        (hash_as_u64(hash) | ones) == self.mask
+    }
 
-        #[allow(clippy::cast_possible_truncation)]
-        #[allow(clippy::cast_sign_loss)]
-        fn mask_bits(num_items: f64, max_items: f64) -> u32 {
-            // for small ratios this can result in a negative number, ensure it returns 0 instead
-            ((num_items / max_items).log2().ceil()).max(0.0) as u32
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.test_mask(hash) && self.filter.contains(hash)
+    }
+}
+
+impl Sanitize for DataFilter {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        if self.mask_bits >= u64::BITS {
+            return Err(SanitizeErrors::MaskBitsOutOfBounds(self.mask_bits));
         }
+        Ok(())
+    }
+}
 
+impl Default for DataFilter {
+    fn default() -> Self {
         let max_items: u32 = 1287;
         let num_items: u32 = 0;
-        let false_rate: f64 = 0.1f64;
         let max_bits = MASK_BITS;
         let mask_bits = mask_bits(f64::from(num_items), f64::from(max_items));
 
-        let bloom: Bloom<Hash> = Bloom::random(num_items as usize, false_rate, max_bits as usize);
+        let bloom: Bloom<Hash> = Bloom::random(num_items as usize, FALSE_RATE, max_bits as usize);
 
         DataFilter {
             filter: bloom,
@@ -49,3 +96,49 @@ impl Default for DataFilter {
         }
     }
 }
+
+// Splits the hash space into 2^mask_bits buckets, one bloom filter each, so
+// that every filter fits in `max_bytes` regardless of how many values we know.
+pub struct DataFilterSet {
+    filters: Vec<Bloom<Hash>>,
+    mask_bits: u32,
+}
+
+impl DataFilterSet {
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn new(num_items: usize, max_bytes: usize) -> Self {
+        let max_bits = (max_bytes * 8) as f64;
+        let max_items = max_items(max_bits, FALSE_RATE, KEYS);
+        let mask_bits = mask_bits(num_items as f64, max_items);
+        let filters = std::iter::repeat_with(|| {
+            Bloom::random(max_items as usize, FALSE_RATE, max_bits as usize)
+        })
+        .take(1 << mask_bits)
+        .collect();
+
+        Self { filters, mask_bits }
+    }
+
+    pub fn add(&mut self, hash: &Hash) {
+        let index = hash_as_u64(hash)
+            .checked_shr(64 - self.mask_bits)
+            .unwrap_or(0);
+        self.filters[index as usize].add(hash);
+    }
+}
+
+impl From<DataFilterSet> for Vec<DataFilter> {
+    fn from(set: DataFilterSet) -> Self {
+        let mask_bits = set.mask_bits;
+        set.filters
+            .into_iter()
+            .enumerate()
+            .map(|(seed, filter)| DataFilter {
+                filter,
+                mask: compute_mask(seed as u64, mask_bits),
+                mask_bits,
+            })
+            .collect()
+    }
+}
@@ -1,14 +1,23 @@
 use {
-    super::{contact_info::ContactInfo, legacy_contact_info::LegacyContactInfo},
-    bincode::serialize,
+    super::{
+        contact_info::ContactInfo,
+        legacy_contact_info::LegacyContactInfo,
+        sanitize::{
+            sanitize_slot, sanitize_wallclock, Sanitize, SanitizeErrors, MAX_DUPLICATE_SHREDS,
+            MAX_EPOCH_SLOTS, MAX_LOWEST_SLOTS, MAX_SLOTS_PER_ENTRY, MAX_VOTES,
+        },
+    },
+    bincode::{deserialize, serialize},
     bv::BitVec,
     serde::{Deserialize, Serialize},
     solana_sdk::{
         clock::Slot,
         hash::Hash,
         pubkey::Pubkey,
+        sanitize::Sanitize as _,
         signature::{Keypair, Signable, Signature},
         transaction::Transaction,
+        vote::{self, instruction::VoteInstruction},
     },
     std::{
         borrow::{Borrow, Cow},
@@ -34,6 +43,27 @@ pub enum GossipData {
     RestartHeaviestFork(RestartHeaviestFork),
 }
 
+impl GossipData {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GossipData::LegacyContactInfo(_) => "LegacyContactInfo",
+            GossipData::Vote(_, _) => "Vote",
+            GossipData::LowestSlot(_, _) => "LowestSlot",
+            GossipData::LegacySnapshotHashes(_) => "LegacySnapshotHashes",
+            GossipData::AccountsHashes(_) => "AccountsHashes",
+            GossipData::EpochSlots(_, _) => "EpochSlots",
+            GossipData::LegacyVersion(_) => "LegacyVersion",
+            GossipData::Version(_) => "Version",
+            GossipData::NodeInstance(_) => "NodeInstance",
+            GossipData::DuplicateShred(_, _) => "DuplicateShred",
+            GossipData::SnapshotHashes(_) => "SnapshotHashes",
+            GossipData::ContactInfo(_) => "ContactInfo",
+            GossipData::RestartLastVotedForkSlots(_) => "RestartLastVotedForkSlots",
+            GossipData::RestartHeaviestFork(_) => "RestartHeaviestFork",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GossipValue {
     pub signature: Signature,
@@ -72,6 +102,115 @@ impl GossipValue {
             GossipData::RestartHeaviestFork(fork) => fork.from,
         }
     }
+
+    pub fn wallclock(&self) -> u64 {
+        match &self.data {
+            GossipData::LegacyContactInfo(contact_info) => contact_info.wallclock(),
+            GossipData::Vote(_, vote) => vote.wallclock,
+            GossipData::LowestSlot(_, slots) => slots.wallclock,
+            GossipData::LegacySnapshotHashes(hash) => hash.wallclock,
+            GossipData::AccountsHashes(hash) => hash.wallclock,
+            GossipData::EpochSlots(_, p) => p.wallclock,
+            GossipData::LegacyVersion(version) => version.wallclock,
+            GossipData::Version(version) => version.wallclock,
+            GossipData::NodeInstance(node) => node.wallclock,
+            GossipData::DuplicateShred(_, shred) => shred.wallclock,
+            GossipData::SnapshotHashes(hash) => hash.wallclock,
+            GossipData::ContactInfo(node) => node.wallclock(),
+            GossipData::RestartLastVotedForkSlots(slots) => slots.wallclock,
+            GossipData::RestartHeaviestFork(fork) => fork.wallclock,
+        }
+    }
+
+    pub fn label(&self) -> GossipLabel {
+        let pubkey = self.pubkey();
+        match &self.data {
+            GossipData::LegacyContactInfo(_) => GossipLabel::LegacyContactInfo(pubkey),
+            GossipData::Vote(index, _) => GossipLabel::Vote(*index, pubkey),
+            GossipData::LowestSlot(_, _) => GossipLabel::LowestSlot(pubkey),
+            GossipData::LegacySnapshotHashes(_) => GossipLabel::LegacySnapshotHashes(pubkey),
+            GossipData::AccountsHashes(_) => GossipLabel::AccountsHashes(pubkey),
+            GossipData::EpochSlots(index, _) => GossipLabel::EpochSlots(*index, pubkey),
+            GossipData::LegacyVersion(_) => GossipLabel::LegacyVersion(pubkey),
+            GossipData::Version(_) => GossipLabel::Version(pubkey),
+            GossipData::NodeInstance(_) => GossipLabel::NodeInstance(pubkey),
+            GossipData::DuplicateShred(index, _) => GossipLabel::DuplicateShred(*index, pubkey),
+            GossipData::SnapshotHashes(_) => GossipLabel::SnapshotHashes(pubkey),
+            GossipData::ContactInfo(_) => GossipLabel::ContactInfo(pubkey),
+            GossipData::RestartLastVotedForkSlots(_) => {
+                GossipLabel::RestartLastVotedForkSlots(pubkey)
+            }
+            GossipData::RestartHeaviestFork(_) => GossipLabel::RestartHeaviestFork(pubkey),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GossipLabel {
+    LegacyContactInfo(Pubkey),
+    Vote(VoteIndex, Pubkey),
+    LowestSlot(Pubkey),
+    LegacySnapshotHashes(Pubkey),
+    AccountsHashes(Pubkey),
+    EpochSlots(EpochSlotsIndex, Pubkey),
+    LegacyVersion(Pubkey),
+    Version(Pubkey),
+    NodeInstance(Pubkey),
+    DuplicateShred(DuplicateShredIndex, Pubkey),
+    SnapshotHashes(Pubkey),
+    ContactInfo(Pubkey),
+    RestartLastVotedForkSlots(Pubkey),
+    RestartHeaviestFork(Pubkey),
+}
+
+impl Sanitize for GossipValue {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        self.data.sanitize()
+    }
+}
+
+impl Sanitize for GossipData {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        match self {
+            GossipData::LegacyContactInfo(contact_info) => contact_info.sanitize(),
+            GossipData::Vote(index, vote) => {
+                if *index >= MAX_VOTES {
+                    return Err(SanitizeErrors::VoteIndexOutOfBounds(*index));
+                }
+                vote.sanitize()
+            }
+            GossipData::LowestSlot(index, slots) => {
+                if *index >= MAX_LOWEST_SLOTS {
+                    return Err(SanitizeErrors::LowestSlotIndexOutOfBounds(*index));
+                }
+                slots.sanitize()
+            }
+            GossipData::LegacySnapshotHashes(hash) => hash.sanitize(),
+            GossipData::AccountsHashes(hash) => hash.sanitize(),
+            GossipData::EpochSlots(index, p) => {
+                if usize::from(*index) >= usize::from(MAX_EPOCH_SLOTS) {
+                    return Err(SanitizeErrors::EpochSlotsIndexOutOfBounds(*index));
+                }
+                p.sanitize()
+            }
+            GossipData::LegacyVersion(version) => sanitize_wallclock(version.wallclock),
+            GossipData::Version(version) => sanitize_wallclock(version.wallclock),
+            GossipData::NodeInstance(node) => sanitize_wallclock(node.wallclock),
+            GossipData::DuplicateShred(index, shred) => {
+                if *index >= MAX_DUPLICATE_SHREDS {
+                    return Err(SanitizeErrors::DuplicateShredIndexOutOfBounds(*index));
+                }
+                shred.sanitize()
+            }
+            GossipData::SnapshotHashes(hash) => hash.sanitize(),
+            GossipData::ContactInfo(node) => node.sanitize(),
+            GossipData::RestartLastVotedForkSlots(slots) => slots.sanitize(),
+            GossipData::RestartHeaviestFork(fork) => {
+                sanitize_wallclock(fork.wallclock)?;
+                sanitize_slot(fork.last_slot)
+            }
+        }
+    }
 }
 
 impl Signable for GossipValue {
@@ -79,7 +218,7 @@ impl Signable for GossipValue {
         self.pubkey()
     }
 
-    fn signable_data(&self) -> Cow<[u8]> {
+    fn signable_data(&self) -> Cow<'_, [u8]> {
         Cow::Owned(serialize(&self.data).expect("failed to serialize CrdsData"))
     }
 
@@ -104,7 +243,45 @@ pub struct Vote {
     pub from: Pubkey,
     transaction: Transaction,
     pub wallclock: u64,
-    slot: Option<Slot>,
+}
+
+impl Vote {
+    pub fn new(from: Pubkey, transaction: Transaction, wallclock: u64) -> Self {
+        Self {
+            from,
+            transaction,
+            wallclock,
+        }
+    }
+
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    // Slots voted on by the first vote instruction in the transaction.
+    pub fn slots(&self) -> Vec<Slot> {
+        let message = &self.transaction.message;
+        message
+            .instructions
+            .iter()
+            .filter(|ix| {
+                message.account_keys.get(usize::from(ix.program_id_index))
+                    == Some(&vote::program::id())
+            })
+            .find_map(|ix| deserialize::<VoteInstruction>(&ix.data).ok())
+            .map(|ix| match ix {
+                VoteInstruction::Vote(vote) | VoteInstruction::VoteSwitch(vote, _) => vote.slots,
+                VoteInstruction::UpdateVoteState(update)
+                | VoteInstruction::UpdateVoteStateSwitch(update, _)
+                | VoteInstruction::CompactUpdateVoteState(update)
+                | VoteInstruction::CompactUpdateVoteStateSwitch(update, _) => update.slots(),
+                VoteInstruction::TowerSync(sync) | VoteInstruction::TowerSyncSwitch(sync, _) => {
+                    sync.slots()
+                }
+                _ => vec![],
+            })
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -131,6 +308,26 @@ enum CompressionType {
     BZip2,
 }
 
+impl Sanitize for Vote {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)?;
+        self.transaction
+            .sanitize()
+            .map_err(SanitizeErrors::InvalidVoteTransaction)
+    }
+}
+
+impl Sanitize for LowestSlot {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)?;
+        sanitize_slot(self.lowest)?;
+        if self.root != 0 || !self.slots.is_empty() || !self.stash.is_empty() {
+            return Err(SanitizeErrors::DeprecatedLowestSlotFields);
+        }
+        Ok(())
+    }
+}
+
 type LegacySnapshotHashes = AccountsHashes;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -140,6 +337,15 @@ pub struct AccountsHashes {
     pub wallclock: u64,
 }
 
+impl Sanitize for AccountsHashes {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)?;
+        self.hashes
+            .iter()
+            .try_for_each(|(slot, _)| sanitize_slot(*slot))
+    }
+}
+
 type EpochSlotsIndex = u8;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -169,6 +375,36 @@ pub struct Uncompressed {
     pub slots: BitVec<u8>,
 }
 
+impl Sanitize for EpochSlots {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)?;
+        self.slots.sanitize()
+    }
+}
+
+impl Sanitize for CompressedSlots {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        match self {
+            CompressedSlots::Flate2(slots) => {
+                if slots.num >= MAX_SLOTS_PER_ENTRY {
+                    return Err(SanitizeErrors::SlotsPerEntryOutOfBounds(slots.num));
+                }
+                sanitize_slot(slots.first_slot)
+            }
+            CompressedSlots::Uncompressed(slots) => {
+                if slots.num >= MAX_SLOTS_PER_ENTRY {
+                    return Err(SanitizeErrors::SlotsPerEntryOutOfBounds(slots.num));
+                }
+                let len = slots.slots.len();
+                if len % 8 != 0 || len > MAX_SLOTS_PER_ENTRY as u64 {
+                    return Err(SanitizeErrors::InvalidSlotsLength(len));
+                }
+                sanitize_slot(slots.first_slot)
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LegacyVersion {
     pub from: Pubkey,
@@ -191,6 +427,32 @@ pub struct NodeInstance {
     token: u64,
 }
 
+impl NodeInstance {
+    pub fn new<R: rand::Rng>(rng: &mut R, from: Pubkey, now: u64) -> Self {
+        Self {
+            from,
+            wallclock: now,
+            timestamp: now,
+            token: rng.gen(),
+        }
+    }
+
+    pub fn with_wallclock(&self, wallclock: u64) -> Self {
+        Self {
+            wallclock,
+            ..self.clone()
+        }
+    }
+
+    pub fn token(&self) -> u64 {
+        self.token
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
 pub type DuplicateShredIndex = u16;
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -205,6 +467,33 @@ pub struct DuplicateShred {
     chunk: Vec<u8>,
 }
 
+impl DuplicateShred {
+    pub fn num_chunks(&self) -> u8 {
+        self.num_chunks
+    }
+
+    pub fn chunk_index(&self) -> u8 {
+        self.chunk_index
+    }
+
+    pub fn chunk(&self) -> &[u8] {
+        &self.chunk
+    }
+}
+
+impl Sanitize for DuplicateShred {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)?;
+        if self.chunk_index >= self.num_chunks {
+            return Err(SanitizeErrors::ChunkIndexOutOfBounds {
+                chunk_index: self.chunk_index,
+                num_chunks: self.num_chunks,
+            });
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 enum ShredType {
     Data = 0b1010_0101,
@@ -219,6 +508,24 @@ pub struct SnapshotHashes {
     pub wallclock: u64,
 }
 
+impl Sanitize for SnapshotHashes {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)?;
+        let full = self.full.0;
+        sanitize_slot(full)?;
+        for (incremental, _) in &self.incremental {
+            sanitize_slot(*incremental)?;
+            if *incremental <= full {
+                return Err(SanitizeErrors::IncrementalSlotNotAfterFull {
+                    full,
+                    incremental: *incremental,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RestartLastVotedForkSlots {
     pub from: Pubkey,
@@ -229,6 +536,21 @@ pub struct RestartLastVotedForkSlots {
     pub shred_version: u16,
 }
 
+impl Sanitize for RestartLastVotedForkSlots {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)?;
+        sanitize_slot(self.last_voted_slot)?;
+        let is_empty = match &self.offsets {
+            SlotsOffsets::RunLengthEncoding(RunLengthEncoding(offsets)) => offsets.is_empty(),
+            SlotsOffsets::RawOffsets(RawOffsets(offsets)) => offsets.is_empty(),
+        };
+        if is_empty {
+            return Err(SanitizeErrors::EmptySlotsOffsets);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 enum SlotsOffsets {
     RunLengthEncoding(RunLengthEncoding),
@@ -250,3 +572,195 @@ pub struct RestartHeaviestFork {
     pub observed_stake: u64,
     pub shred_version: u16,
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::gossip_data::sanitize::{MAX_SLOT, MAX_WALLCLOCK},
+        solana_sdk::timing::timestamp,
+    };
+
+    fn new_vote(wallclock: u64) -> Vote {
+        Vote {
+            from: Pubkey::new_unique(),
+            transaction: Transaction::new_with_payer(&[], Some(&Pubkey::new_unique())),
+            wallclock,
+        }
+    }
+
+    fn new_duplicate_shred(chunk_index: u8, num_chunks: u8) -> DuplicateShred {
+        DuplicateShred {
+            from: Pubkey::new_unique(),
+            wallclock: timestamp(),
+            slot: 0,
+            _unused: 0,
+            _unused_shred_type: ShredType::Code,
+            num_chunks,
+            chunk_index,
+            chunk: vec![],
+        }
+    }
+
+    fn new_snapshot_hashes(full: Slot, incremental: Vec<Slot>) -> SnapshotHashes {
+        SnapshotHashes {
+            from: Pubkey::new_unique(),
+            full: (full, Hash::default()),
+            incremental: incremental
+                .into_iter()
+                .map(|slot| (slot, Hash::default()))
+                .collect(),
+            wallclock: timestamp(),
+        }
+    }
+
+    #[test]
+    fn test_sanitize_vote_index() {
+        let data = GossipData::Vote(MAX_VOTES - 1, new_vote(timestamp()));
+        assert_eq!(data.sanitize(), Ok(()));
+
+        let data = GossipData::Vote(MAX_VOTES, new_vote(timestamp()));
+        assert_eq!(
+            data.sanitize(),
+            Err(SanitizeErrors::VoteIndexOutOfBounds(MAX_VOTES))
+        );
+    }
+
+    // Vote as encoded by validators: signature, variant 1, index, from, the
+    // transaction and wallclock. Nothing follows the wallclock.
+    #[test]
+    fn test_vote_wire_format() {
+        let vote = Vote {
+            from: Pubkey::new_from_array([1; 32]),
+            transaction: Transaction::new_with_payer(&[], Some(&Pubkey::new_from_array([2; 32]))),
+            wallclock: 0x0102_0304_0506_0708,
+        };
+        let value = GossipValue {
+            signature: Signature::default(),
+            data: GossipData::Vote(5, vote),
+        };
+        let mut expected = vec![0u8; 64];
+        expected.extend([1, 0, 0, 0, 5]);
+        expected.extend([1; 32]);
+        // One empty signature, header, one account key, blockhash, no instructions.
+        expected.push(1);
+        expected.extend([0; 64]);
+        expected.extend([1, 0, 0, 1]);
+        expected.extend([2; 32]);
+        expected.extend([0; 32]);
+        expected.push(0);
+        expected.extend([8, 7, 6, 5, 4, 3, 2, 1]);
+
+        assert_eq!(serialize(&value).unwrap(), expected);
+        let decoded: GossipValue = deserialize(&expected).unwrap();
+        assert_eq!(decoded.wallclock(), 0x0102_0304_0506_0708);
+        assert_eq!(serialize(&decoded).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_vote_slots() {
+        let authority = Pubkey::new_unique();
+        let ix = vote::instruction::vote(
+            &Pubkey::new_unique(),
+            &authority,
+            vote::state::Vote::new(vec![7, 8, 9], Hash::default()),
+        );
+        let vote = Vote {
+            from: authority,
+            transaction: Transaction::new_with_payer(&[ix], Some(&authority)),
+            wallclock: timestamp(),
+        };
+        assert_eq!(vote.slots(), vec![7, 8, 9]);
+
+        let value = GossipValue::new_signed(GossipData::Vote(0, vote), &Keypair::new());
+        let bytes = serialize(&value).unwrap();
+        let decoded: GossipValue = deserialize(&bytes).unwrap();
+        assert_eq!(serialize(&decoded).unwrap(), bytes);
+        assert!(new_vote(timestamp()).slots().is_empty());
+    }
+
+    #[test]
+    fn test_sanitize_wallclock() {
+        let data = GossipData::Vote(0, new_vote(MAX_WALLCLOCK));
+        assert_eq!(
+            data.sanitize(),
+            Err(SanitizeErrors::WallclockOutOfBounds(MAX_WALLCLOCK))
+        );
+    }
+
+    #[test]
+    fn test_sanitize_epoch_slots_and_lowest_slot_index() {
+        let epoch_slots = EpochSlots {
+            from: Pubkey::new_unique(),
+            slots: vec![],
+            wallclock: timestamp(),
+        };
+        let data = GossipData::EpochSlots(MAX_EPOCH_SLOTS - 1, epoch_slots.clone());
+        assert_eq!(data.sanitize(), Ok(()));
+        let data = GossipData::EpochSlots(MAX_EPOCH_SLOTS, epoch_slots);
+        assert_eq!(
+            data.sanitize(),
+            Err(SanitizeErrors::EpochSlotsIndexOutOfBounds(MAX_EPOCH_SLOTS))
+        );
+
+        let lowest_slot = LowestSlot {
+            from: Pubkey::new_unique(),
+            root: 0,
+            lowest: 10,
+            slots: BTreeSet::new(),
+            stash: vec![],
+            wallclock: timestamp(),
+        };
+        let data = GossipData::LowestSlot(0, lowest_slot.clone());
+        assert_eq!(data.sanitize(), Ok(()));
+        let data = GossipData::LowestSlot(1, lowest_slot);
+        assert_eq!(
+            data.sanitize(),
+            Err(SanitizeErrors::LowestSlotIndexOutOfBounds(1))
+        );
+    }
+
+    #[test]
+    fn test_sanitize_duplicate_shred_chunk_index() {
+        let data = GossipData::DuplicateShred(0, new_duplicate_shred(2, 3));
+        assert_eq!(data.sanitize(), Ok(()));
+
+        let data = GossipData::DuplicateShred(0, new_duplicate_shred(3, 3));
+        assert_eq!(
+            data.sanitize(),
+            Err(SanitizeErrors::ChunkIndexOutOfBounds {
+                chunk_index: 3,
+                num_chunks: 3
+            })
+        );
+
+        let data = GossipData::DuplicateShred(MAX_DUPLICATE_SHREDS, new_duplicate_shred(0, 1));
+        assert_eq!(
+            data.sanitize(),
+            Err(SanitizeErrors::DuplicateShredIndexOutOfBounds(
+                MAX_DUPLICATE_SHREDS
+            ))
+        );
+    }
+
+    #[test]
+    fn test_sanitize_snapshot_hashes() {
+        let data = GossipData::SnapshotHashes(new_snapshot_hashes(100, vec![101, 150]));
+        assert_eq!(data.sanitize(), Ok(()));
+
+        let data = GossipData::SnapshotHashes(new_snapshot_hashes(100, vec![150, 100]));
+        assert_eq!(
+            data.sanitize(),
+            Err(SanitizeErrors::IncrementalSlotNotAfterFull {
+                full: 100,
+                incremental: 100
+            })
+        );
+
+        let data = GossipData::SnapshotHashes(new_snapshot_hashes(MAX_SLOT, vec![]));
+        assert_eq!(
+            data.sanitize(),
+            Err(SanitizeErrors::SlotOutOfBounds(MAX_SLOT))
+        );
+    }
+}
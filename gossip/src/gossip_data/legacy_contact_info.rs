@@ -1,4 +1,5 @@
 use {
+    super::sanitize::{sanitize_wallclock, Sanitize, SanitizeErrors},
     serde::{Deserialize, Serialize},
     solana_sdk::pubkey::Pubkey,
     std::net::SocketAddr,
@@ -25,4 +26,54 @@ impl LegacyContactInfo {
     pub fn pubkey(&self) -> &Pubkey {
         &self.id
     }
+
+    pub fn wallclock(&self) -> u64 {
+        self.wallclock
+    }
+
+    pub fn shred_version(&self) -> u16 {
+        self.shred_version
+    }
+
+    pub fn gossip(&self) -> Option<SocketAddr> {
+        specified(self.gossip)
+    }
+
+    pub fn rpc(&self) -> Option<SocketAddr> {
+        specified(self.rpc)
+    }
+
+    pub fn tpu(&self) -> Option<SocketAddr> {
+        specified(self.tpu)
+    }
+
+    // Every advertised socket by name, skipping unused ones.
+    pub fn socket_addrs(&self) -> Vec<(&'static str, SocketAddr)> {
+        [
+            ("gossip", self.gossip),
+            ("tvu", self.tvu),
+            ("tvu_quic", self.tvu_quic),
+            ("serve_repair_quic", self.serve_repair_quic),
+            ("tpu", self.tpu),
+            ("tpu_forwards", self.tpu_forwards),
+            ("tpu_vote", self.tpu_vote),
+            ("rpc", self.rpc),
+            ("rpc_pubsub", self.rpc_pubsub),
+            ("serve_repair", self.serve_repair),
+        ]
+        .into_iter()
+        .filter_map(|(name, addr)| Some((name, specified(addr)?)))
+        .collect()
+    }
+}
+
+// Unused sockets are advertised as 0.0.0.0:0.
+fn specified(addr: SocketAddr) -> Option<SocketAddr> {
+    (!addr.ip().is_unspecified() && addr.port() != 0).then_some(addr)
+}
+
+impl Sanitize for LegacyContactInfo {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)
+    }
 }
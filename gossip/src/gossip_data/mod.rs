@@ -1,4 +1,6 @@
 pub mod contact_info;
 pub mod filter;
+#[allow(clippy::module_inception)]
 pub mod gossip_data;
 pub mod legacy_contact_info;
+pub mod sanitize;
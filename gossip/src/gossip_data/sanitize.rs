@@ -0,0 +1,81 @@
+use {
+    solana_sdk::{clock::Slot, sanitize::SanitizeError},
+    std::net::IpAddr,
+    thiserror::Error,
+};
+
+pub const MAX_WALLCLOCK: u64 = 1_000_000_000_000_000;
+pub const MAX_SLOT: u64 = 1_000_000_000_000_000;
+pub const MAX_VOTES: u8 = 32;
+pub const MAX_EPOCH_SLOTS: u8 = 255;
+pub const MAX_LOWEST_SLOTS: u8 = 1;
+pub const MAX_DUPLICATE_SHREDS: u16 = 512;
+pub const MAX_SLOTS_PER_ENTRY: usize = 2048 * 8;
+
+pub trait Sanitize {
+    fn sanitize(&self) -> Result<(), SanitizeErrors>;
+}
+
+impl<T: Sanitize> Sanitize for Vec<T> {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        self.iter().try_for_each(Sanitize::sanitize)
+    }
+}
+
+pub fn sanitize_wallclock(wallclock: u64) -> Result<(), SanitizeErrors> {
+    if wallclock >= MAX_WALLCLOCK {
+        return Err(SanitizeErrors::WallclockOutOfBounds(wallclock));
+    }
+    Ok(())
+}
+
+pub fn sanitize_slot(slot: Slot) -> Result<(), SanitizeErrors> {
+    if slot >= MAX_SLOT {
+        return Err(SanitizeErrors::SlotOutOfBounds(slot));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SanitizeErrors {
+    #[error("Wallclock {0} is out of bounds")]
+    WallclockOutOfBounds(u64),
+    #[error("Slot {0} is out of bounds")]
+    SlotOutOfBounds(Slot),
+    #[error("Vote index {0} is out of bounds")]
+    VoteIndexOutOfBounds(u8),
+    #[error("Lowest slot index {0} is out of bounds")]
+    LowestSlotIndexOutOfBounds(u8),
+    #[error("Epoch slots index {0} is out of bounds")]
+    EpochSlotsIndexOutOfBounds(u8),
+    #[error("Duplicate shred index {0} is out of bounds")]
+    DuplicateShredIndexOutOfBounds(u16),
+    #[error("Duplicate shred chunk index {chunk_index} is not below num chunks {num_chunks}")]
+    ChunkIndexOutOfBounds { chunk_index: u8, num_chunks: u8 },
+    #[error("Lowest slot carries deprecated fields")]
+    DeprecatedLowestSlotFields,
+    #[error("Number of slots {0} per entry is out of bounds")]
+    SlotsPerEntryOutOfBounds(usize),
+    #[error("Uncompressed slots bitvec has an invalid length {0}")]
+    InvalidSlotsLength(u64),
+    #[error("Incremental snapshot slot {incremental} is not later than full slot {full}")]
+    IncrementalSlotNotAfterFull { full: Slot, incremental: Slot },
+    #[error("Restart slots offsets are empty")]
+    EmptySlotsOffsets,
+    #[error("Invalid vote transaction: {0}")]
+    InvalidVoteTransaction(SanitizeError),
+    #[error("Duplicate ip address {0} in contact info")]
+    DuplicateIpAddr(IpAddr),
+    #[error("Duplicate socket key {0} in contact info")]
+    DuplicateSocket(u8),
+    #[error("Socket ip index {0} is out of bounds")]
+    IpIndexOutOfBounds(u8),
+    #[error("Contact info has an unused ip address")]
+    UnusedIpAddr,
+    #[error("Contact info port offsets overflow")]
+    PortOffsetsOverflow,
+    #[error("Filter mask bits {0} is out of bounds")]
+    MaskBitsOutOfBounds(u32),
+    #[error("Prune message sender does not match prune data pubkey")]
+    PruneSenderMismatch,
+}
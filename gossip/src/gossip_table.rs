@@ -0,0 +1,251 @@
+use {
+    crate::gossip_data::gossip_data::{GossipLabel, GossipValue},
+    bincode::serialize,
+    solana_sdk::{
+        hash::{self, Hash},
+        pubkey::Pubkey,
+    },
+    std::collections::{hash_map::Entry, HashMap, HashSet},
+    thiserror::Error,
+};
+
+#[derive(Clone, Debug)]
+pub struct GossipEntry {
+    pub value: GossipValue,
+    pub hash: Hash,
+    pub local_timestamp: u64,
+}
+
+pub fn hash_value(value: &GossipValue) -> Result<Hash, GossipTableErrors> {
+    let bytes = serialize(value).map_err(|_| GossipTableErrors::SerializeFailed)?;
+    Ok(hash::hash(&bytes))
+}
+
+impl GossipEntry {
+    fn new(value: GossipValue, local_timestamp: u64) -> Result<Self, GossipTableErrors> {
+        Ok(Self {
+            hash: hash_value(&value)?,
+            value,
+            local_timestamp,
+        })
+    }
+
+    fn overrides(&self, other: &Self) -> bool {
+        match self.value.wallclock().cmp(&other.value.wallclock()) {
+            std::cmp::Ordering::Greater => true,
+            std::cmp::Ordering::Less => false,
+            std::cmp::Ordering::Equal => self.hash > other.hash,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GossipTable {
+    table: HashMap<GossipLabel, GossipEntry>,
+}
+
+impl GossipTable {
+    // Returns the value that was replaced, if any.
+    pub fn insert(
+        &mut self,
+        value: GossipValue,
+        now: u64,
+    ) -> Result<Option<GossipValue>, GossipTableErrors> {
+        let label = value.label();
+        let entry = GossipEntry::new(value, now)?;
+        match self.table.entry(label) {
+            Entry::Vacant(vacant) => {
+                vacant.insert(entry);
+                Ok(None)
+            }
+            Entry::Occupied(mut occupied) => {
+                if entry.overrides(occupied.get()) {
+                    Ok(Some(occupied.insert(entry).value))
+                } else if entry.hash == occupied.get().hash {
+                    Err(GossipTableErrors::DuplicateValue)
+                } else {
+                    Err(GossipTableErrors::OutdatedValue)
+                }
+            }
+        }
+    }
+
+    // Marks a value as received again at `now`, for duplicates that still
+    // show the origin is around.
+    pub fn refresh(&mut self, label: &GossipLabel, now: u64) {
+        if let Some(entry) = self.table.get_mut(label) {
+            entry.local_timestamp = entry.local_timestamp.max(now);
+        }
+    }
+
+    pub fn get(&self, label: &GossipLabel) -> Option<&GossipEntry> {
+        self.table.get(label)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &GossipEntry> {
+        self.table.values()
+    }
+
+    // Removes values we haven't received an update for since `timeout` ago,
+    // except the ones from `keep`. Like the validator, every value of a node
+    // whose contact info is still fresh is kept, since slow changing values
+    // such as snapshot hashes are rarely resent.
+    pub fn purge(&mut self, keep: &Pubkey, now: u64, timeout: u64) -> Vec<GossipValue> {
+        let cutoff = now.saturating_sub(timeout);
+        let live: HashSet<Pubkey> = self
+            .table
+            .iter()
+            .filter(|(label, entry)| {
+                matches!(
+                    label,
+                    GossipLabel::ContactInfo(_) | GossipLabel::LegacyContactInfo(_)
+                ) && entry.local_timestamp >= cutoff
+            })
+            .map(|(_, entry)| entry.value.pubkey())
+            .collect();
+        let expired: Vec<GossipLabel> = self
+            .table
+            .iter()
+            .filter(|(_, entry)| {
+                let pubkey = entry.value.pubkey();
+                entry.local_timestamp < cutoff && pubkey != *keep && !live.contains(&pubkey)
+            })
+            .map(|(label, _)| *label)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|label| self.table.remove(&label))
+            .map(|entry| entry.value)
+            .collect()
+    }
+
+    // Removes every value from `pubkey`.
+    pub fn remove_pubkey(&mut self, pubkey: &Pubkey) -> Vec<GossipValue> {
+        let labels: Vec<GossipLabel> = self
+            .table
+            .iter()
+            .filter(|(_, entry)| entry.value.pubkey() == *pubkey)
+            .map(|(label, _)| *label)
+            .collect();
+        labels
+            .into_iter()
+            .filter_map(|label| self.table.remove(&label))
+            .map(|entry| entry.value)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GossipTableErrors {
+    #[error("Value is older than the one in the table")]
+    OutdatedValue,
+    #[error("Value is already in the table")]
+    DuplicateValue,
+    #[error("Failed to serialize value")]
+    SerializeFailed,
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::gossip_data::{
+            contact_info::ContactInfo,
+            gossip_data::{GossipData, SnapshotHashes},
+        },
+        solana_sdk::{signature::Keypair, signer::Signer, timing::timestamp},
+    };
+
+    fn new_contact_info(keypair: &Keypair, wallclock: u64) -> GossipValue {
+        let gossip = "127.0.0.1:8001".parse().unwrap();
+        let contact_info = ContactInfo::new(keypair.pubkey(), wallclock, 0, gossip);
+        GossipValue::new_signed(GossipData::ContactInfo(contact_info), keypair)
+    }
+
+    #[test]
+    fn test_insert_newer_value_overrides() {
+        let keypair = Keypair::new();
+        let now = timestamp();
+        let mut table = GossipTable::default();
+
+        let value = new_contact_info(&keypair, now);
+        let label = value.label();
+        assert!(table.insert(value.clone(), now).unwrap().is_none());
+        assert_eq!(
+            table.insert(value, now).unwrap_err(),
+            GossipTableErrors::DuplicateValue
+        );
+        assert_eq!(
+            table
+                .insert(new_contact_info(&keypair, now - 1), now)
+                .unwrap_err(),
+            GossipTableErrors::OutdatedValue
+        );
+        let replaced = table
+            .insert(new_contact_info(&keypair, now + 1), now)
+            .unwrap();
+        assert_eq!(replaced.unwrap().wallclock(), now);
+
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get(&label).unwrap().value.wallclock(), now + 1);
+    }
+
+    #[test]
+    fn test_purge_expired_values() {
+        let keypair = Keypair::new();
+        let other = Keypair::new();
+        let now = timestamp();
+        let mut table = GossipTable::default();
+
+        table
+            .insert(new_contact_info(&keypair, now), now - 100)
+            .unwrap();
+        table
+            .insert(new_contact_info(&other, now), now - 100)
+            .unwrap();
+        assert!(table.purge(&keypair.pubkey(), now, 200).is_empty());
+
+        let purged = table.purge(&keypair.pubkey(), now, 50);
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].pubkey(), other.pubkey());
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_purge_keeps_values_of_live_nodes() {
+        let keypair = Keypair::new();
+        let now = timestamp();
+        let mut table = GossipTable::default();
+        let hashes = SnapshotHashes {
+            from: keypair.pubkey(),
+            full: (1, Hash::default()),
+            incremental: vec![],
+            wallclock: now,
+        };
+        let hashes = GossipValue::new_signed(GossipData::SnapshotHashes(hashes), &keypair);
+        table.insert(hashes.clone(), now - 100).unwrap();
+        let contact_info = new_contact_info(&keypair, now);
+        table.insert(contact_info.clone(), now - 100).unwrap();
+
+        // Resent in a pull response, the contact info stays fresh.
+        assert_eq!(
+            table.insert(contact_info.clone(), now).unwrap_err(),
+            GossipTableErrors::DuplicateValue
+        );
+        table.refresh(&contact_info.label(), now);
+        assert!(table.purge(&Pubkey::new_unique(), now, 50).is_empty());
+        assert!(table.get(&hashes.label()).is_some());
+
+        // Once the node goes quiet, all of its values go.
+        assert_eq!(table.purge(&Pubkey::new_unique(), now + 100, 50).len(), 2);
+        assert!(table.is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use {
+    crate::{
+        gossip_data::{
+            contact_info::ContactInfo,
+            filter::DataFilter,
+            gossip_data::{
+                AccountsHashes, DuplicateShred, EpochSlots, GossipData, GossipValue, LegacyVersion,
+                LowestSlot, NodeInstance, RestartHeaviestFork, RestartLastVotedForkSlots,
+                SnapshotHashes, Version, Vote,
+            },
+            legacy_contact_info::LegacyContactInfo,
+        },
+        gossip_table::GossipEntry,
+        ping_pong::{Ping, Pong},
+        prune::PruneData,
+    },
+    solana_sdk::pubkey::Pubkey,
+    std::net::SocketAddr,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandlerAction {
+    Continue,
+    Drop,
+}
+
+// Hooks into incoming traffic. Message callbacks run right after decoding,
+// before any checks. Value callbacks run once a value passed sanitize,
+// signature and wallclock checks, before it goes into the table. Returning
+// `Drop` from either stops the message or value from being processed.
+// `on_accepted` runs last, once a value is in the table, and can't drop it.
+//
+// Every callback defaults to `Continue`. `on_value` dispatches to the per
+// variant callbacks, so override one or the other.
+#[allow(unused_variables)]
+pub trait GossipHandler: Send + Sync {
+    fn on_pull_request(
+        &self,
+        from: SocketAddr,
+        filter: &DataFilter,
+        value: &GossipValue,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_pull_response(
+        &self,
+        from: SocketAddr,
+        sender: &Pubkey,
+        values: &[GossipValue],
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_push_message(
+        &self,
+        from: SocketAddr,
+        sender: &Pubkey,
+        values: &[GossipValue],
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_prune_message(
+        &self,
+        from: SocketAddr,
+        sender: &Pubkey,
+        data: &PruneData,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_ping(&self, from: SocketAddr, ping: &Ping) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_pong(&self, from: SocketAddr, pong: &Pong) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_value(&self, from: SocketAddr, value: &GossipValue) -> HandlerAction {
+        dispatch_value(self, from, value)
+    }
+
+    fn on_legacy_contact_info(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        contact_info: &LegacyContactInfo,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_vote(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        index: u8,
+        vote: &Vote,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_lowest_slot(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        lowest_slot: &LowestSlot,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_legacy_snapshot_hashes(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        hashes: &AccountsHashes,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_accounts_hashes(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        hashes: &AccountsHashes,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_epoch_slots(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        index: u8,
+        epoch_slots: &EpochSlots,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_legacy_version(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        version: &LegacyVersion,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_version(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        version: &Version,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_node_instance(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        instance: &NodeInstance,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_duplicate_shred(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        index: u16,
+        shred: &DuplicateShred,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_snapshot_hashes(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        hashes: &SnapshotHashes,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_contact_info(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        contact_info: &ContactInfo,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_restart_last_voted_fork_slots(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        slots: &RestartLastVotedForkSlots,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_restart_heaviest_fork(
+        &self,
+        from: SocketAddr,
+        value: &GossipValue,
+        fork: &RestartHeaviestFork,
+    ) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn on_accepted(&self, entry: &GossipEntry) {}
+}
+
+pub fn dispatch_value<H: GossipHandler + ?Sized>(
+    handler: &H,
+    from: SocketAddr,
+    value: &GossipValue,
+) -> HandlerAction {
+    match &value.data {
+        GossipData::LegacyContactInfo(data) => handler.on_legacy_contact_info(from, value, data),
+        GossipData::Vote(index, data) => handler.on_vote(from, value, *index, data),
+        GossipData::LowestSlot(_, data) => handler.on_lowest_slot(from, value, data),
+        GossipData::LegacySnapshotHashes(data) => {
+            handler.on_legacy_snapshot_hashes(from, value, data)
+        }
+        GossipData::AccountsHashes(data) => handler.on_accounts_hashes(from, value, data),
+        GossipData::EpochSlots(index, data) => handler.on_epoch_slots(from, value, *index, data),
+        GossipData::LegacyVersion(data) => handler.on_legacy_version(from, value, data),
+        GossipData::Version(data) => handler.on_version(from, value, data),
+        GossipData::NodeInstance(data) => handler.on_node_instance(from, value, data),
+        GossipData::DuplicateShred(index, data) => {
+            handler.on_duplicate_shred(from, value, *index, data)
+        }
+        GossipData::SnapshotHashes(data) => handler.on_snapshot_hashes(from, value, data),
+        GossipData::ContactInfo(data) => handler.on_contact_info(from, value, data),
+        GossipData::RestartLastVotedForkSlots(data) => {
+            handler.on_restart_last_voted_fork_slots(from, value, data)
+        }
+        GossipData::RestartHeaviestFork(data) => {
+            handler.on_restart_heaviest_fork(from, value, data)
+        }
+    }
+}
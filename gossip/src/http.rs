@@ -0,0 +1,669 @@
+use {
+    crate::{
+        connection::DropReason,
+        error::GossipErrorKind,
+        events::{EventErrors, EventFilter, EventReceiver, GossipEventKind},
+        gossip_data::gossip_data::{GossipData, GossipLabel},
+        gossip_table::{GossipEntry, GossipTable},
+        json::{data_json, entry_json, event_json, iso_time},
+        metrics,
+        node::GossipNode,
+    },
+    axum::{
+        extract::{
+            ws::{Message, WebSocket, WebSocketUpgrade},
+            Path, Query, State,
+        },
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+        routing::get,
+        Json, Router,
+    },
+    serde::Deserialize,
+    serde_json::{json, Map, Value},
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        collections::{BTreeMap, HashMap},
+        io,
+        net::SocketAddr,
+        str::FromStr,
+        sync::Arc,
+    },
+    tokio::net::TcpListener,
+};
+
+// Filters shared by the node listings. `version` matches whole leading
+// components, so `2.1` selects every 2.1.x release but not 2.10.x.
+#[derive(Debug, Default, Deserialize)]
+pub struct NodeQuery {
+    pub shred_version: Option<u16>,
+    pub version: Option<String>,
+    pub has_rpc: Option<bool>,
+}
+
+impl NodeQuery {
+    fn matches(&self, node: &Value) -> bool {
+        if let Some(shred_version) = self.shred_version {
+            if node["shred_version"] != shred_version {
+                return false;
+            }
+        }
+        if let Some(version) = &self.version {
+            match node["version"].as_str() {
+                Some(node_version) if version_matches(node_version, version) => (),
+                _ => return false,
+            }
+        }
+        match self.has_rpc {
+            Some(has_rpc) => node["sockets"].get("rpc").is_some() == has_rpc,
+            None => true,
+        }
+    }
+}
+
+fn version_matches(version: &str, prefix: &str) -> bool {
+    match version.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+// Subscription filters of the event feed, as comma separated lists:
+// `/events?kinds=vote,duplicate_shred&pubkeys=<pubkey>,<pubkey>`.
+#[derive(Debug, Default, Deserialize)]
+pub struct EventQuery {
+    pub kinds: Option<String>,
+    pub pubkeys: Option<String>,
+}
+
+impl EventQuery {
+    fn filter(&self) -> Result<EventFilter, String> {
+        let mut filter = EventFilter::default();
+        if let Some(kinds) = &self.kinds {
+            let kinds = kinds
+                .split(',')
+                .map(|kind| {
+                    GossipEventKind::parse(kind).ok_or_else(|| format!("Unknown event {}", kind))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            filter = filter.kinds(kinds);
+        }
+        if let Some(pubkeys) = &self.pubkeys {
+            let pubkeys = pubkeys
+                .split(',')
+                .map(|pubkey| {
+                    Pubkey::from_str(pubkey).map_err(|_| format!("Invalid pubkey {}", pubkey))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            filter = filter.pubkeys(pubkeys);
+        }
+        Ok(filter)
+    }
+}
+
+pub fn router(node: Arc<GossipNode>) -> Router {
+    Router::new()
+        .route("/nodes", get(get_nodes))
+        .route("/nodes/{pubkey}", get(get_node))
+        .route("/votes/latest", get(get_latest_votes))
+        .route("/versions", get(get_versions))
+        .route("/snapshots", get(get_snapshots))
+        .route("/stats", get(get_stats))
+        .route("/metrics", get(get_metrics))
+        .route("/events", get(get_events))
+        .with_state(node)
+}
+
+// Only `/metrics`, for scraping on a port apart from the API.
+pub fn metrics_router(node: Arc<GossipNode>) -> Router {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .with_state(node)
+}
+
+pub async fn serve(node: Arc<GossipNode>, addr: SocketAddr) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("HTTP API listening on {}", listener.local_addr()?);
+    axum::serve(listener, router(node)).await
+}
+
+pub async fn serve_metrics(node: Arc<GossipNode>, addr: SocketAddr) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("Metrics listening on {}", listener.local_addr()?);
+    axum::serve(listener, metrics_router(node)).await
+}
+
+async fn get_metrics(State(node): State<Arc<GossipNode>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::encode(&node),
+    )
+}
+
+async fn get_events(
+    State(node): State<Arc<GossipNode>>,
+    Query(query): Query<EventQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, String)> {
+    let filter = query
+        .filter()
+        .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    // Subscribe before the upgrade so no event after the handshake is missed.
+    let events = node.subscribe_with(filter);
+    Ok(upgrade.on_upgrade(move |socket| stream_events(socket, events)))
+}
+
+// Forwards events until either side closes. A client too slow to keep up
+// is told how many events it missed instead of being disconnected.
+async fn stream_events(mut socket: WebSocket, mut events: EventReceiver) {
+    loop {
+        let message = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => event_json(&event),
+                Err(EventErrors::Lagged(missed)) => json!({"type": "lagged", "missed": missed}),
+                Err(EventErrors::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum, anything else is ignored.
+                Some(Ok(_)) => continue,
+            },
+        };
+        if let Err(err) = socket.send(Message::Text(message.to_string().into())).await {
+            log::debug!("Event subscriber went away: {}", err);
+            break;
+        }
+    }
+}
+
+async fn get_nodes(
+    State(node): State<Arc<GossipNode>>,
+    Query(query): Query<NodeQuery>,
+) -> Json<Value> {
+    let table = node.table.read().unwrap();
+    Json(nodes(&table, &query).into())
+}
+
+async fn get_node(
+    State(node): State<Arc<GossipNode>>,
+    Path(pubkey): Path<String>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let pubkey = Pubkey::from_str(&pubkey).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid pubkey {}", pubkey),
+        )
+    })?;
+    let table = node.table.read().unwrap();
+    let values: Vec<Value> = sorted(
+        table
+            .entries()
+            .filter(|entry| entry.value.pubkey() == pubkey),
+    )
+    .into_iter()
+    .map(entry_json)
+    .collect();
+    if values.is_empty() {
+        return Err((StatusCode::NOT_FOUND, format!("Unknown node {}", pubkey)));
+    }
+    let mut node =
+        node_json(&table, &pubkey).unwrap_or_else(|| json!({"pubkey": pubkey.to_string()}));
+    node["values"] = values.into();
+    Ok(Json(node))
+}
+
+async fn get_latest_votes(State(node): State<Arc<GossipNode>>) -> Json<Value> {
+    let table = node.table.read().unwrap();
+    Json(latest_votes(&table).into())
+}
+
+async fn get_versions(
+    State(node): State<Arc<GossipNode>>,
+    Query(query): Query<NodeQuery>,
+) -> Json<Value> {
+    let table = node.table.read().unwrap();
+    Json(versions(&table, &query))
+}
+
+async fn get_snapshots(State(node): State<Arc<GossipNode>>) -> Json<Value> {
+    let table = node.table.read().unwrap();
+    Json(snapshots(&table).into())
+}
+async fn get_stats(State(node): State<Arc<GossipNode>>) -> Json<Value> {
+    let (num_values, values, num_nodes) = {
+        let table = node.table.read().unwrap();
+        let mut values = BTreeMap::<&str, usize>::new();
+        for entry in table.entries() {
+            *values.entry(entry.value.data.name()).or_default() += 1;
+        }
+        (
+            table.len(),
+            values,
+            nodes(&table, &NodeQuery::default()).len(),
+        )
+    };
+    let errors: Map<String, Value> = GossipErrorKind::ALL
+        .iter()
+        .map(|kind| (kind.as_str().to_string(), node.errors.get(*kind).into()))
+        .collect();
+    let drops: Map<String, Value> = DropReason::ALL
+        .iter()
+        .map(|reason| {
+            let drops = node.connection.drops().get(*reason);
+            (reason.as_str().to_string(), drops.into())
+        })
+        .collect();
+    Json(json!({
+        "pubkey": node.pubkey().to_string(),
+        "num_nodes": num_nodes,
+        "num_values": num_values,
+        "values": values,
+        "errors": errors,
+        "drops": drops,
+        "signatures": {
+            "verified": node.verify_stats.verified(),
+            "failed": node.verify_stats.failed(),
+        },
+    }))
+}
+
+// One object per node, from its contact info. Nodes only advertising the
+// legacy contact info take their version from the separate version value.
+pub fn nodes(table: &GossipTable, query: &NodeQuery) -> Vec<Value> {
+    let mut pubkeys: Vec<Pubkey> = table
+        .entries()
+        .filter_map(|entry| match &entry.value.data {
+            GossipData::ContactInfo(_) | GossipData::LegacyContactInfo(_) => {
+                Some(entry.value.pubkey())
+            }
+            _ => None,
+        })
+        .collect();
+    pubkeys.sort_unstable();
+    pubkeys.dedup();
+    pubkeys
+        .iter()
+        .filter_map(|pubkey| node_json(table, pubkey))
+        .filter(|node| query.matches(node))
+        .collect()
+}
+
+fn node_json(table: &GossipTable, pubkey: &Pubkey) -> Option<Value> {
+    let entry = table
+        .get(&GossipLabel::ContactInfo(*pubkey))
+        .or_else(|| table.get(&GossipLabel::LegacyContactInfo(*pubkey)))?;
+    let mut node = data_json(&entry.value.data);
+    if node.get("version").is_none() {
+        node["version"] = table
+            .get(&GossipLabel::Version(*pubkey))
+            .or_else(|| table.get(&GossipLabel::LegacyVersion(*pubkey)))
+            .map_or(Value::Null, |entry| {
+                data_json(&entry.value.data)["version"].clone()
+            });
+    }
+    node["pubkey"] = json!(pubkey.to_string());
+    node["wallclock"] = iso_time(entry.value.wallclock());
+    node["last_seen"] = iso_time(entry.local_timestamp);
+    Some(node)
+}
+
+// The highest voted slot of every node, newest first.
+pub fn latest_votes(table: &GossipTable) -> Vec<Value> {
+    let mut latest = HashMap::<Pubkey, (u64, &GossipEntry)>::new();
+    for entry in table.entries() {
+        let GossipData::Vote(_, vote) = &entry.value.data else {
+            continue;
+        };
+        let Some(slot) = vote.slots().into_iter().max() else {
+            continue;
+        };
+        let best = latest.entry(vote.from).or_insert((slot, entry));
+        if slot > best.0 {
+            *best = (slot, entry);
+        }
+    }
+    let mut latest: Vec<(Pubkey, u64, &GossipEntry)> = latest
+        .into_iter()
+        .map(|(pubkey, (slot, entry))| (pubkey, slot, entry))
+        .collect();
+    latest.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    latest
+        .into_iter()
+        .map(|(pubkey, slot, entry)| {
+            json!({
+                "pubkey": pubkey.to_string(),
+                "slot": slot,
+                "wallclock": iso_time(entry.value.wallclock()),
+                "last_seen": iso_time(entry.local_timestamp),
+            })
+        })
+        .collect()
+}
+
+// Number of nodes per version.
+pub fn versions(table: &GossipTable, query: &NodeQuery) -> Value {
+    let mut versions = BTreeMap::<String, usize>::new();
+    for node in nodes(table, query) {
+        let version = node["version"].as_str().unwrap_or("unknown").to_string();
+        *versions.entry(version).or_default() += 1;
+    }
+    json!(versions)
+}
+
+// Snapshot hashes of every node, highest full snapshot slot first.
+pub fn snapshots(table: &GossipTable) -> Vec<Value> {
+    let mut snapshots: Vec<(u64, Value)> = table
+        .entries()
+        .filter_map(|entry| match &entry.value.data {
+            GossipData::SnapshotHashes(hashes) => {
+                let mut snapshot = data_json(&entry.value.data);
+                snapshot["pubkey"] = json!(hashes.from.to_string());
+                snapshot["wallclock"] = iso_time(hashes.wallclock);
+                Some((hashes.full.0, snapshot))
+            }
+            _ => None,
+        })
+        .collect();
+    snapshots.sort_unstable_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then_with(|| a.1["pubkey"].as_str().cmp(&b.1["pubkey"].as_str()))
+    });
+    snapshots
+        .into_iter()
+        .map(|(_, snapshot)| snapshot)
+        .collect()
+}
+
+fn sorted<'a>(entries: impl Iterator<Item = &'a GossipEntry>) -> Vec<&'a GossipEntry> {
+    let mut entries: Vec<&GossipEntry> = entries.collect();
+    entries.sort_by_cached_key(|entry| {
+        (
+            entry.value.data.name(),
+            format!("{:?}", entry.value.label()),
+        )
+    });
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::gossip_data::{
+            contact_info::ContactInfo,
+            gossip_data::{GossipValue, LegacyVersion, SnapshotHashes, Vote},
+            legacy_contact_info::LegacyContactInfo,
+        },
+        solana_sdk::{
+            hash::Hash, signature::Keypair, signer::Signer, timing::timestamp,
+            transaction::Transaction, vote,
+        },
+    };
+
+    fn insert_node(table: &mut GossipTable, shred_version: u16) -> Pubkey {
+        let keypair = Keypair::new();
+        let gossip = "10.0.0.1:8001".parse().unwrap();
+        let contact_info = ContactInfo::new(keypair.pubkey(), timestamp(), shred_version, gossip);
+        let value = GossipValue::new_signed(GossipData::ContactInfo(contact_info), &keypair);
+        table.insert(value, timestamp()).unwrap();
+        keypair.pubkey()
+    }
+
+    #[test]
+    fn test_nodes_query() {
+        let mut table = GossipTable::default();
+        let pubkey = insert_node(&mut table, 1);
+        insert_node(&mut table, 2);
+
+        assert_eq!(nodes(&table, &NodeQuery::default()).len(), 2);
+        let query = NodeQuery {
+            shred_version: Some(1),
+            ..NodeQuery::default()
+        };
+        let found = nodes(&table, &query);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0]["pubkey"], pubkey.to_string());
+        assert_eq!(found[0]["sockets"]["gossip"], "10.0.0.1:8001");
+
+        let query = NodeQuery {
+            has_rpc: Some(true),
+            ..NodeQuery::default()
+        };
+        assert!(nodes(&table, &query).is_empty());
+        let query = NodeQuery {
+            version: Some("no-such-version".to_string()),
+            ..NodeQuery::default()
+        };
+        assert!(nodes(&table, &query).is_empty());
+
+        assert!(version_matches("2.1.7", "2.1"));
+        assert!(version_matches("2.1.7", "2.1.7"));
+        assert!(!version_matches("2.10.0", "2.1"));
+        assert!(!version_matches("2.11.3", "2.1"));
+    }
+
+    fn insert_vote(table: &mut GossipTable, keypair: &Keypair, index: u8, slots: Vec<u64>) {
+        let ix = vote::instruction::vote(
+            &Pubkey::new_unique(),
+            &keypair.pubkey(),
+            vote::state::Vote::new(slots, Hash::default()),
+        );
+        let transaction = Transaction::new_with_payer(&[ix], Some(&keypair.pubkey()));
+        let vote = Vote::new(keypair.pubkey(), transaction, timestamp());
+        let value = GossipValue::new_signed(GossipData::Vote(index, vote), keypair);
+        table.insert(value, timestamp()).unwrap();
+    }
+
+    #[test]
+    fn test_latest_votes() {
+        let mut table = GossipTable::default();
+        let (early, late) = (Keypair::new(), Keypair::new());
+        insert_vote(&mut table, &late, 0, vec![4, 5]);
+        insert_vote(&mut table, &late, 1, vec![9]);
+        insert_vote(&mut table, &early, 0, vec![7]);
+        insert_vote(&mut table, &Keypair::new(), 0, vec![]);
+
+        let votes: Vec<(Value, Value)> = latest_votes(&table)
+            .into_iter()
+            .map(|vote| (vote["pubkey"].clone(), vote["slot"].clone()))
+            .collect();
+        assert_eq!(
+            votes,
+            vec![
+                (json!(late.pubkey().to_string()), json!(9)),
+                (json!(early.pubkey().to_string()), json!(7)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_versions_and_snapshots() {
+        let mut table = GossipTable::default();
+        insert_node(&mut table, 0);
+        let version = nodes(&table, &NodeQuery::default())[0]["version"].clone();
+
+        // A node only known from the legacy contact info and version.
+        let legacy = Keypair::new();
+        let contact_info: LegacyContactInfo = serde_json::from_value(json!({
+            "id": legacy.pubkey(),
+            "gossip": "10.0.0.2:8001",
+            "tvu": "0.0.0.0:0",
+            "tvu_quic": "0.0.0.0:0",
+            "serve_repair_quic": "0.0.0.0:0",
+            "tpu": "0.0.0.0:0",
+            "tpu_forwards": "0.0.0.0:0",
+            "tpu_vote": "0.0.0.0:0",
+            "rpc": "0.0.0.0:0",
+            "rpc_pubsub": "0.0.0.0:0",
+            "serve_repair": "0.0.0.0:0",
+            "wallclock": timestamp(),
+            "shred_version": 0,
+        }))
+        .unwrap();
+        let legacy_version = LegacyVersion {
+            from: legacy.pubkey(),
+            wallclock: timestamp(),
+            version: serde_json::from_value(
+                json!({"major": 1, "minor": 2, "patch": 3, "commit": null}),
+            )
+            .unwrap(),
+        };
+        for data in [
+            GossipData::LegacyContactInfo(contact_info),
+            GossipData::LegacyVersion(legacy_version),
+        ] {
+            let value = GossipValue::new_signed(data, &legacy);
+            table.insert(value, timestamp()).unwrap();
+        }
+        let mut expected = Map::new();
+        expected.insert(version.as_str().unwrap().to_string(), json!(1));
+        expected.insert("1.2.3".to_string(), json!(1));
+        assert_eq!(
+            versions(&table, &NodeQuery::default()),
+            Value::Object(expected)
+        );
+
+        for (keypair, full) in [(Keypair::new(), 10), (Keypair::new(), 20)] {
+            let hashes = SnapshotHashes {
+                from: keypair.pubkey(),
+                full: (full, Hash::default()),
+                incremental: vec![],
+                wallclock: timestamp(),
+            };
+            let value = GossipValue::new_signed(GossipData::SnapshotHashes(hashes), &keypair);
+            table.insert(value, timestamp()).unwrap();
+        }
+        let full_slots: Vec<Value> = snapshots(&table)
+            .into_iter()
+            .map(|snapshot| snapshot["full"]["slot"].clone())
+            .collect();
+        assert_eq!(full_slots, vec![json!(20), json!(10)]);
+    }
+
+    async fn http_get(addr: SocketAddr, path: &str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    // Reads one unmasked, unfragmented text frame sent by the server.
+    async fn read_text_frame(stream: &mut tokio::net::TcpStream) -> String {
+        use tokio::io::AsyncReadExt;
+        let mut header = [0u8; 2];
+        stream.read_exact(&mut header).await.unwrap();
+        assert_eq!(header[0], 0x81);
+        let len = match header[1] {
+            126 => stream.read_u16().await.unwrap() as usize,
+            127 => stream.read_u64().await.unwrap() as usize,
+            len => len as usize,
+        };
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).await.unwrap();
+        String::from_utf8(payload).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_events_feed() {
+        use {
+            crate::events::GossipEvent,
+            tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        };
+        let connection = crate::connection::Connection::new("127.0.0.1:0")
+            .await
+            .unwrap();
+        let node = GossipNode::new(
+            connection,
+            Keypair::new(),
+            crate::node::NodeConfig::default(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(std::future::IntoFuture::into_future(axum::serve(
+            listener,
+            router(node.clone()),
+        )));
+
+        let response = http_get(addr, "/events?kinds=no_such_event").await;
+        assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+
+        let watched = Pubkey::new_unique();
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "GET /events?kinds=node_left&pubkeys={} HTTP/1.1\r\n\
+             Host: localhost\r\n\
+             Connection: Upgrade\r\n\
+             Upgrade: websocket\r\n\
+             Sec-WebSocket-Version: 13\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            watched
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut reader = BufReader::new(&mut stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("HTTP/1.1 101"), "{}", line);
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+        }
+        assert!(reader.buffer().is_empty());
+
+        node.emit(vec![
+            GossipEvent::NodeLeft(Pubkey::new_unique()),
+            GossipEvent::NodeLeft(watched),
+        ]);
+        let event: Value = serde_json::from_str(&read_text_frame(&mut stream).await).unwrap();
+        assert_eq!(
+            event,
+            json!({"type": "node_left", "pubkey": watched.to_string()})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_serve_api() {
+        let connection = crate::connection::Connection::new("127.0.0.1:0")
+            .await
+            .unwrap();
+        let node = GossipNode::new(
+            connection,
+            Keypair::new(),
+            crate::node::NodeConfig::default(),
+        );
+        let pubkey = insert_node(&mut node.table.write().unwrap(), 0);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(std::future::IntoFuture::into_future(axum::serve(
+            listener,
+            router(node),
+        )));
+
+        let response = http_get(addr, &format!("/nodes/{}", pubkey)).await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains("\"sockets\""));
+        let response = http_get(addr, &format!("/nodes/{}", Pubkey::new_unique())).await;
+        assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+        let response = http_get(addr, "/nodes/not-a-pubkey").await;
+        assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+        let response = http_get(addr, "/stats").await;
+        assert!(response.contains("\"num_nodes\":1"), "{}", response);
+        let response = http_get(addr, "/metrics").await;
+        assert!(
+            response.contains("text/plain; version=0.0.4"),
+            "{}",
+            response
+        );
+        assert!(
+            response.contains("dubstep_table_entries{type=\"ContactInfo\"} 1\n"),
+            "{}",
+            response
+        );
+    }
+}
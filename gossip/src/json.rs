@@ -0,0 +1,291 @@
+use {
+    crate::{
+        events::GossipEvent,
+        gossip_data::{
+            contact_info::{socket_tag_name, ContactInfo},
+            gossip_data::{
+                CompressedSlots, DuplicateShred, GossipData, GossipValue, SnapshotHashes, Vote,
+            },
+        },
+        gossip_table::{GossipEntry, GossipTable},
+    },
+    chrono::{DateTime, SecondsFormat},
+    serde_json::{json, Map, Value},
+    solana_sdk::{clock::Slot, hash::Hash, timing::timestamp},
+    solana_version::LegacyVersion2,
+    std::net::SocketAddr,
+};
+
+// Milliseconds since the epoch as RFC 3339, or null when out of range.
+pub fn iso_time(ms: u64) -> Value {
+    i64::try_from(ms)
+        .ok()
+        .and_then(DateTime::from_timestamp_millis)
+        .map_or(Value::Null, |time| {
+            Value::String(time.to_rfc3339_opts(SecondsFormat::Millis, true))
+        })
+}
+
+impl GossipTable {
+    // Every entry in a stable order, for tools that can't link this crate.
+    pub fn snapshot_json(&self) -> Value {
+        let mut entries: Vec<&GossipEntry> = self.entries().collect();
+        entries.sort_by_cached_key(|entry| {
+            (
+                entry.value.data.name(),
+                entry.value.pubkey(),
+                format!("{:?}", entry.value.label()),
+            )
+        });
+        json!({
+            "timestamp": iso_time(timestamp()),
+            "num_values": entries.len(),
+            "values": entries.into_iter().map(entry_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+pub fn entry_json(entry: &GossipEntry) -> Value {
+    let mut value = value_json(&entry.value);
+    value["hash"] = json!(entry.hash.to_string());
+    value["local_timestamp"] = iso_time(entry.local_timestamp);
+    value
+}
+
+pub fn value_json(value: &GossipValue) -> Value {
+    json!({
+        "type": value.data.name(),
+        "pubkey": value.pubkey().to_string(),
+        "signature": value.signature.to_string(),
+        "wallclock": iso_time(value.wallclock()),
+        "data": data_json(&value.data),
+    })
+}
+
+pub fn data_json(data: &GossipData) -> Value {
+    match data {
+        GossipData::LegacyContactInfo(contact_info) => json!({
+            "shred_version": contact_info.shred_version(),
+            "sockets": sockets_json(contact_info.socket_addrs()),
+        }),
+        GossipData::ContactInfo(contact_info) => contact_info_json(contact_info),
+        GossipData::Vote(index, vote) => vote_json(*index, vote),
+        GossipData::LowestSlot(index, slots) => json!({
+            "index": index,
+            "lowest": slots.lowest,
+        }),
+        GossipData::LegacySnapshotHashes(hashes) | GossipData::AccountsHashes(hashes) => json!({
+            "hashes": slot_hashes_json(&hashes.hashes),
+        }),
+        GossipData::EpochSlots(index, slots) => json!({
+            "index": index,
+            "slots": slots.slots.iter().map(|slots| match slots {
+                CompressedSlots::Flate2(slots) => json!({
+                    "compression": "flate2",
+                    "first_slot": slots.first_slot,
+                    "num": slots.num,
+                }),
+                CompressedSlots::Uncompressed(slots) => json!({
+                    "compression": "none",
+                    "first_slot": slots.first_slot,
+                    "num": slots.num,
+                }),
+            }).collect::<Vec<_>>(),
+        }),
+        // Same shape as `Version`, legacy versions have no feature set.
+        GossipData::LegacyVersion(version) => json!({
+            "version": LegacyVersion2::from(version.version.clone()).to_string(),
+        }),
+        GossipData::Version(version) => json!({
+            "version": version.version.to_string(),
+            "feature_set": version.version.feature_set,
+        }),
+        GossipData::NodeInstance(instance) => json!({
+            "timestamp": iso_time(instance.timestamp()),
+            "token": instance.token(),
+        }),
+        GossipData::DuplicateShred(index, shred) => duplicate_shred_json(*index, shred),
+        GossipData::SnapshotHashes(hashes) => snapshot_hashes_json(hashes),
+        GossipData::RestartLastVotedForkSlots(slots) => json!({
+            "last_voted_slot": slots.last_voted_slot,
+            "last_voted_hash": slots.last_voted_hash.to_string(),
+            "shred_version": slots.shred_version,
+        }),
+        GossipData::RestartHeaviestFork(fork) => json!({
+            "last_slot": fork.last_slot,
+            "last_slot_hash": fork.last_slot_hash.to_string(),
+            "observed_stake": fork.observed_stake,
+            "shred_version": fork.shred_version,
+        }),
+    }
+}
+
+pub fn contact_info_json(contact_info: &ContactInfo) -> Value {
+    json!({
+        "shred_version": contact_info.shred_version(),
+        "version": contact_info.version().to_string(),
+        "sockets": sockets_json(contact_info.socket_addrs().map(|(tag, addr)| {
+            let name = socket_tag_name(tag).map_or_else(|| format!("tag_{}", tag), String::from);
+            (name, addr)
+        })),
+    })
+}
+
+pub fn vote_json(index: u8, vote: &Vote) -> Value {
+    json!({
+        "index": index,
+        "slots": vote.slots(),
+        "transaction": vote.transaction().signatures.first().map(ToString::to_string),
+    })
+}
+
+pub fn duplicate_shred_json(index: u16, shred: &DuplicateShred) -> Value {
+    json!({
+        "index": index,
+        "slot": shred.slot,
+        "num_chunks": shred.num_chunks(),
+        "chunk_index": shred.chunk_index(),
+        "chunk_len": shred.chunk().len(),
+    })
+}
+
+pub fn snapshot_hashes_json(hashes: &SnapshotHashes) -> Value {
+    json!({
+        "full": slot_hash_json(&hashes.full),
+        "incremental": slot_hashes_json(&hashes.incremental),
+    })
+}
+
+// An event with its type, origin and the fields of the changed value.
+pub fn event_json(event: &GossipEvent) -> Value {
+    let mut value = match event {
+        GossipEvent::NodeJoined(contact_info) => json!({
+            "contact_info": contact_info_json(contact_info),
+            "wallclock": iso_time(contact_info.wallclock()),
+        }),
+        GossipEvent::NodeLeft(_) => json!({}),
+        GossipEvent::ContactInfoChanged { old, new } => json!({
+            "old": contact_info_json(old),
+            "new": contact_info_json(new),
+            "wallclock": iso_time(new.wallclock()),
+        }),
+        GossipEvent::Vote { index, vote, .. } => {
+            let mut value = vote_json(*index, vote);
+            value["wallclock"] = iso_time(vote.wallclock);
+            value
+        }
+        GossipEvent::SnapshotHashes(hashes) => {
+            let mut value = snapshot_hashes_json(hashes);
+            value["wallclock"] = iso_time(hashes.wallclock);
+            value
+        }
+        GossipEvent::DuplicateShred { index, shred, .. } => {
+            let mut value = duplicate_shred_json(*index, shred);
+            value["wallclock"] = iso_time(shred.wallclock);
+            value
+        }
+        GossipEvent::VersionChanged {
+            previous, version, ..
+        } => json!({
+            "previous": previous,
+            "version": version,
+        }),
+    };
+    value["type"] = json!(event.kind().as_str());
+    value["pubkey"] = json!(event.pubkey().to_string());
+    value
+}
+
+fn sockets_json<S: Into<String>>(sockets: impl IntoIterator<Item = (S, SocketAddr)>) -> Value {
+    let sockets: Map<String, Value> = sockets
+        .into_iter()
+        .map(|(name, addr)| (name.into(), json!(addr.to_string())))
+        .collect();
+    Value::Object(sockets)
+}
+
+fn slot_hash_json((slot, hash): &(Slot, Hash)) -> Value {
+    json!({"slot": slot, "hash": hash.to_string()})
+}
+
+fn slot_hashes_json(hashes: &[(Slot, Hash)]) -> Value {
+    hashes.iter().map(slot_hash_json).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::gossip_data::{
+            contact_info::ContactInfo,
+            gossip_data::{LegacyVersion, NodeInstance},
+        },
+        solana_sdk::{signature::Keypair, signer::Signer},
+    };
+
+    #[test]
+    fn test_legacy_version_json() {
+        let version = LegacyVersion {
+            from: Keypair::new().pubkey(),
+            wallclock: 0,
+            version: serde_json::from_value(
+                json!({"major": 1, "minor": 2, "patch": 3, "commit": 7}),
+            )
+            .unwrap(),
+        };
+        assert_eq!(
+            data_json(&GossipData::LegacyVersion(version)),
+            json!({"version": "1.2.3"})
+        );
+    }
+
+    #[test]
+    fn test_snapshot_json() {
+        let keypair = Keypair::new();
+        let now = 1_700_000_000_000;
+        let gossip: SocketAddr = "10.0.0.1:8001".parse().unwrap();
+        let contact_info = ContactInfo::new(keypair.pubkey(), now, 42, gossip);
+        let instance = NodeInstance::new(&mut rand::thread_rng(), keypair.pubkey(), now);
+        let mut table = GossipTable::default();
+        for data in [
+            GossipData::NodeInstance(instance),
+            GossipData::ContactInfo(contact_info),
+        ] {
+            table
+                .insert(GossipValue::new_signed(data, &keypair), now)
+                .unwrap();
+        }
+
+        let snapshot = table.snapshot_json();
+        assert_eq!(snapshot["num_values"], 2);
+        let value = &snapshot["values"][0];
+        assert_eq!(value["type"], "ContactInfo");
+        assert_eq!(value["pubkey"], keypair.pubkey().to_string());
+        assert_eq!(value["wallclock"], "2023-11-14T22:13:20.000Z");
+        assert_eq!(value["data"]["shred_version"], 42);
+        assert_eq!(value["data"]["sockets"]["gossip"], "10.0.0.1:8001");
+        assert_eq!(snapshot["values"][1]["type"], "NodeInstance");
+        assert_eq!(iso_time(u64::MAX), Value::Null);
+    }
+
+    #[test]
+    fn test_event_json() {
+        let pubkey = solana_sdk::pubkey::Pubkey::new_unique();
+        let gossip: SocketAddr = "10.0.0.1:8001".parse().unwrap();
+        let contact_info = ContactInfo::new(pubkey, 1_700_000_000_000, 42, gossip);
+        let event = event_json(&GossipEvent::NodeJoined(contact_info));
+        assert_eq!(event["type"], "node_joined");
+        assert_eq!(event["pubkey"], pubkey.to_string());
+        assert_eq!(event["wallclock"], "2023-11-14T22:13:20.000Z");
+        assert_eq!(event["contact_info"]["sockets"]["gossip"], "10.0.0.1:8001");
+
+        let event = event_json(&GossipEvent::VersionChanged {
+            from: pubkey,
+            previous: None,
+            version: "2.1.0".to_string(),
+        });
+        assert_eq!(event["type"], "version_changed");
+        assert_eq!(event["previous"], Value::Null);
+        assert_eq!(event["version"], "2.1.0");
+    }
+}
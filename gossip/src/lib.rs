@@ -1,6 +1,32 @@
+pub mod capture;
 pub mod connection;
+pub mod error;
+pub mod events;
 pub mod gossip_data;
+pub mod gossip_table;
+pub mod handler;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod metrics;
+#[cfg(target_os = "linux")]
+pub mod mmsg;
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock_validator;
+pub mod node;
+pub mod peer_sampler;
 pub mod ping_pong;
 pub mod process_message;
 pub mod protocol;
+pub mod prune;
 pub mod pull_request;
+pub mod push_active_set;
+pub mod push_message;
+pub mod received_cache;
+pub mod stakes;
+#[cfg(feature = "sqlite")]
+pub mod store;
+pub mod transport;
+pub mod verify;
+pub mod wallclock;
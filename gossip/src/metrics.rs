@@ -0,0 +1,334 @@
+use {
+    crate::{connection::DropReason, error::GossipErrorKind, node::GossipNode},
+    std::{
+        collections::BTreeMap,
+        fmt::Write,
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    },
+};
+
+// Protocol messages start with their variant index as a little-endian u32,
+// so traffic can be classified without decoding it.
+const PROTOCOL_NAMES: [&str; 6] = [
+    "pull_request",
+    "pull_response",
+    "push_message",
+    "prune_message",
+    "ping",
+    "pong",
+];
+const UNKNOWN_PROTOCOL: usize = PROTOCOL_NAMES.len();
+
+const PING_RTT_BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+const PULL_ROUND_BUCKETS: [f64; 8] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1];
+
+fn protocol_index(packet: &[u8]) -> usize {
+    packet
+        .get(..4)
+        .map(|tag| u32::from_le_bytes(tag.try_into().unwrap()) as usize)
+        .filter(|index| *index < PROTOCOL_NAMES.len())
+        .unwrap_or(UNKNOWN_PROTOCOL)
+}
+
+#[derive(Debug, Default)]
+pub struct TrafficStats {
+    packets: [AtomicU64; PROTOCOL_NAMES.len() + 1],
+    bytes: AtomicU64,
+}
+
+impl TrafficStats {
+    pub fn record(&self, packet: &[u8]) {
+        self.packets[protocol_index(packet)].fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(packet.len() as u64, Ordering::Relaxed);
+    }
+
+    pub fn packets(&self) -> u64 {
+        self.packets
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    fn by_protocol(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        PROTOCOL_NAMES
+            .iter()
+            .chain(&["unknown"])
+            .zip(&self.packets)
+            .map(|(name, count)| (*name, count.load(Ordering::Relaxed)))
+    }
+}
+
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    // Cumulative counts are computed on encode, each bucket only counts its
+    // own range. The last one is +Inf.
+    buckets: Vec<AtomicU64>,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: std::iter::repeat_with(AtomicU64::default)
+                .take(bounds.len() + 1)
+                .collect(),
+            sum_micros: AtomicU64::default(),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let index = self
+            .bounds
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    fn encode(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.buckets) {
+            cumulative += count.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let count = self.count();
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+#[derive(Debug)]
+pub struct NodeMetrics {
+    pub pings_sent: AtomicU64,
+    pub pongs_matched: AtomicU64,
+    pub pongs_unmatched: AtomicU64,
+    pub ping_rtt: Histogram,
+    pub pull_round: Histogram,
+}
+
+impl Default for NodeMetrics {
+    fn default() -> Self {
+        Self {
+            pings_sent: AtomicU64::default(),
+            pongs_matched: AtomicU64::default(),
+            pongs_unmatched: AtomicU64::default(),
+            ping_rtt: Histogram::new(&PING_RTT_BUCKETS),
+            pull_round: Histogram::new(&PULL_ROUND_BUCKETS),
+        }
+    }
+}
+
+impl NodeMetrics {
+    // Records a verified pong, with the round trip when it answered a ping.
+    pub fn record_pong(&self, rtt: Option<Duration>) {
+        match rtt {
+            Some(rtt) => {
+                self.pongs_matched.fetch_add(1, Ordering::Relaxed);
+                self.ping_rtt.observe(rtt);
+            }
+            None => {
+                self.pongs_unmatched.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+struct Encoder {
+    out: String,
+}
+
+impl Encoder {
+    fn header(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    fn metric(&mut self, name: &str, kind: &str, help: &str, value: u64) {
+        self.header(name, kind, help);
+        let _ = writeln!(self.out, "{} {}", name, value);
+    }
+
+    fn labeled<'a>(
+        &mut self,
+        name: &str,
+        kind: &str,
+        help: &str,
+        label: &str,
+        values: impl IntoIterator<Item = (&'a str, u64)>,
+    ) {
+        self.header(name, kind, help);
+        for (value_label, value) in values {
+            let _ = writeln!(
+                self.out,
+                "{}{{{}=\"{}\"}} {}",
+                name, label, value_label, value
+            );
+        }
+    }
+}
+
+// Every node metric in the Prometheus text exposition format.
+pub fn encode(node: &GossipNode) -> String {
+    let mut encoder = Encoder { out: String::new() };
+    let connection = &node.connection;
+
+    encoder.labeled(
+        "dubstep_packets_in_total",
+        "counter",
+        "Packets received by message type.",
+        "type",
+        connection.traffic_in().by_protocol(),
+    );
+    encoder.labeled(
+        "dubstep_packets_out_total",
+        "counter",
+        "Packets sent by message type.",
+        "type",
+        connection.traffic_out().by_protocol(),
+    );
+    encoder.metric(
+        "dubstep_bytes_in_total",
+        "counter",
+        "Bytes received.",
+        connection.traffic_in().bytes(),
+    );
+    encoder.metric(
+        "dubstep_bytes_out_total",
+        "counter",
+        "Bytes sent.",
+        connection.traffic_out().bytes(),
+    );
+    encoder.labeled(
+        "dubstep_drops_total",
+        "counter",
+        "Packets dropped by the connection.",
+        "reason",
+        DropReason::ALL
+            .iter()
+            .map(|reason| (reason.as_str(), connection.drops().get(*reason))),
+    );
+    encoder.labeled(
+        "dubstep_errors_total",
+        "counter",
+        "Messages and values rejected or failed, including decode and signature errors.",
+        "kind",
+        GossipErrorKind::ALL
+            .iter()
+            .map(|kind| (kind.as_str(), node.errors.get(*kind))),
+    );
+    encoder.labeled(
+        "dubstep_signatures_total",
+        "counter",
+        "Gossip value signatures checked.",
+        "result",
+        [
+            ("verified", node.verify_stats.verified()),
+            ("failed", node.verify_stats.failed()),
+        ],
+    );
+
+    let mut entries = BTreeMap::<&str, u64>::new();
+    for entry in node.table.read().unwrap().entries() {
+        *entries.entry(entry.value.data.name()).or_default() += 1;
+    }
+    encoder.labeled(
+        "dubstep_table_entries",
+        "gauge",
+        "Gossip table entries by value type.",
+        "type",
+        entries,
+    );
+
+    encoder.metric(
+        "dubstep_push_queue_dropped_total",
+        "counter",
+        "Values not relayed because the push queue was full.",
+        node.push_queue.lock().unwrap().dropped(),
+    );
+
+    let metrics = &node.metrics;
+    encoder.metric(
+        "dubstep_pings_sent_total",
+        "counter",
+        "Pings sent to verify peers.",
+        metrics.pings_sent.load(Ordering::Relaxed),
+    );
+    encoder.labeled(
+        "dubstep_pongs_total",
+        "counter",
+        "Verified pongs received, by whether they answered a pending ping.",
+        "result",
+        [
+            ("matched", metrics.pongs_matched.load(Ordering::Relaxed)),
+            ("unmatched", metrics.pongs_unmatched.load(Ordering::Relaxed)),
+        ],
+    );
+    metrics.ping_rtt.encode(
+        &mut encoder.out,
+        "dubstep_ping_rtt_seconds",
+        "Round trip time of answered pings.",
+    );
+    metrics.pull_round.encode(
+        &mut encoder.out,
+        "dubstep_pull_round_duration_seconds",
+        "Time to build and queue the pull requests of a round.",
+    );
+    encoder.out
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::protocol::Protocol, bincode::serialize, solana_sdk::signature::Keypair};
+
+    #[test]
+    fn test_traffic_stats() {
+        let ping = crate::ping_pong::Ping::rand(&Keypair::new()).unwrap();
+        let packet = serialize(&Protocol::PingMessage(ping)).unwrap();
+        let stats = TrafficStats::default();
+        stats.record(&packet);
+        stats.record(&[9, 0, 0, 0]);
+        stats.record(&[]);
+
+        let counts: Vec<(&str, u64)> = stats.by_protocol().filter(|(_, n)| *n > 0).collect();
+        assert_eq!(counts, vec![("ping", 1), ("unknown", 2)]);
+        assert_eq!(stats.packets(), 3);
+        assert_eq!(stats.bytes(), packet.len() as u64 + 4);
+    }
+
+    #[test]
+    fn test_histogram_encode() {
+        let histogram = Histogram::new(&[0.01, 0.1]);
+        histogram.observe(Duration::from_millis(5));
+        histogram.observe(Duration::from_millis(50));
+        histogram.observe(Duration::from_secs(2));
+
+        let mut out = String::new();
+        histogram.encode(&mut out, "rtt", "help");
+        assert!(out.contains("rtt_bucket{le=\"0.01\"} 1\n"));
+        assert!(out.contains("rtt_bucket{le=\"0.1\"} 2\n"));
+        assert!(out.contains("rtt_bucket{le=\"+Inf\"} 3\n"));
+        assert!(out.contains("rtt_sum 2.055\n"));
+        assert!(out.contains("rtt_count 3\n"));
+    }
+}
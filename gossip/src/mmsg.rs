@@ -0,0 +1,105 @@
+use {
+    crate::connection::Packet,
+    libc::{iovec, mmsghdr, sockaddr_storage, socklen_t, MSG_DONTWAIT},
+    socket2::SockAddr,
+    std::{io, mem, net::SocketAddr, os::fd::AsRawFd},
+    tokio::net::UdpSocket,
+};
+
+// Reads up to `bufs.len()` datagrams in one syscall. Returns the received
+// size and source of each, in the order of `bufs`.
+pub fn recv_mmsg(socket: &UdpSocket, bufs: &mut [Vec<u8>]) -> io::Result<Vec<(usize, SocketAddr)>> {
+    let mut iovs: Vec<iovec> = bufs
+        .iter_mut()
+        .map(|buf| iovec {
+            iov_base: buf.as_mut_ptr().cast(),
+            iov_len: buf.len(),
+        })
+        .collect();
+    // SAFETY: sockaddr_storage is plain old data.
+    let mut addrs: Vec<sockaddr_storage> = vec![unsafe { mem::zeroed() }; bufs.len()];
+    let mut hdrs: Vec<mmsghdr> = iovs
+        .iter_mut()
+        .zip(addrs.iter_mut())
+        .map(|(iov, addr)| {
+            // SAFETY: mmsghdr is plain old data.
+            let mut hdr: mmsghdr = unsafe { mem::zeroed() };
+            hdr.msg_hdr.msg_name = (addr as *mut sockaddr_storage).cast();
+            hdr.msg_hdr.msg_namelen = mem::size_of::<sockaddr_storage>() as socklen_t;
+            hdr.msg_hdr.msg_iov = iov;
+            hdr.msg_hdr.msg_iovlen = 1;
+            hdr
+        })
+        .collect();
+
+    // SAFETY: every header points at an iovec and address that outlive the call.
+    let num = unsafe {
+        libc::recvmmsg(
+            socket.as_raw_fd(),
+            hdrs.as_mut_ptr(),
+            hdrs.len() as u32,
+            MSG_DONTWAIT,
+            std::ptr::null_mut(),
+        )
+    };
+    if num < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    hdrs.iter()
+        .zip(addrs)
+        .take(num as usize)
+        .map(|(hdr, addr)| {
+            // SAFETY: the kernel filled `addr` and set its length.
+            let addr = unsafe { SockAddr::new(addr, hdr.msg_hdr.msg_namelen) };
+            let addr = addr.as_socket().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "unsupported source address")
+            })?;
+            Ok((hdr.msg_len as usize, addr))
+        })
+        .collect()
+}
+
+// Writes `packets` in one syscall. Returns how many were sent, which can be
+// fewer than requested.
+pub fn send_mmsg(socket: &UdpSocket, packets: &[Packet]) -> io::Result<usize> {
+    let mut iovs: Vec<iovec> = packets
+        .iter()
+        .map(|(data, _)| iovec {
+            iov_base: data.as_ptr() as *mut _,
+            iov_len: data.len(),
+        })
+        .collect();
+    let addrs: Vec<SockAddr> = packets
+        .iter()
+        .map(|(_, addr)| SockAddr::from(*addr))
+        .collect();
+    let mut hdrs: Vec<mmsghdr> = iovs
+        .iter_mut()
+        .zip(&addrs)
+        .map(|(iov, addr)| {
+            // SAFETY: mmsghdr is plain old data.
+            let mut hdr: mmsghdr = unsafe { mem::zeroed() };
+            hdr.msg_hdr.msg_name = addr.as_ptr() as *mut _;
+            hdr.msg_hdr.msg_namelen = addr.len();
+            hdr.msg_hdr.msg_iov = iov;
+            hdr.msg_hdr.msg_iovlen = 1;
+            hdr
+        })
+        .collect();
+
+    // SAFETY: every header points at an iovec and address that outlive the
+    // call, and the kernel only reads from the data buffers.
+    let num = unsafe {
+        libc::sendmmsg(
+            socket.as_raw_fd(),
+            hdrs.as_mut_ptr(),
+            hdrs.len() as u32,
+            MSG_DONTWAIT,
+        )
+    };
+    if num < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(num as usize)
+}
@@ -0,0 +1,168 @@
+use {
+    crate::{
+        connection::Connection,
+        gossip_data::{
+            contact_info::ContactInfo,
+            gossip_data::{GossipData, GossipValue},
+        },
+        gossip_table::hash_value,
+        ping_pong::Ping,
+        protocol::Protocol,
+        push_message::{create_pull_responses, create_push_messages, PushErrors},
+        transport::Transport,
+    },
+    bincode::{deserialize, serialize},
+    solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, timing::timestamp},
+    std::{
+        io,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    },
+};
+
+// Scriptable stand-in for a validator's gossip service, so tests can run
+// without a live cluster. It answers pings with pongs, answers pull requests
+// with its own contact info plus the canned values, and pushes on demand.
+pub struct MockValidator {
+    keypair: Arc<Keypair>,
+    connection: Arc<Connection>,
+    shred_version: u16,
+    values: Mutex<Vec<GossipValue>>,
+    num_pings: AtomicU64,
+    num_pull_requests: AtomicU64,
+    num_push_messages: AtomicU64,
+}
+
+impl MockValidator {
+    pub async fn bind(addr: &str, shred_version: u16) -> io::Result<Arc<Self>> {
+        Ok(Self::new(Connection::new(addr).await?, shred_version))
+    }
+
+    pub fn with_transport(transport: Arc<dyn Transport>, shred_version: u16) -> Arc<Self> {
+        Self::new(Connection::with_transport(transport), shred_version)
+    }
+
+    fn new(connection: Arc<Connection>, shred_version: u16) -> Arc<Self> {
+        connection.start_sending();
+        connection.start_receiving();
+        let validator = Arc::new(Self {
+            keypair: Arc::new(Keypair::new()),
+            connection,
+            shred_version,
+            values: Mutex::default(),
+            num_pings: AtomicU64::default(),
+            num_pull_requests: AtomicU64::default(),
+            num_push_messages: AtomicU64::default(),
+        });
+        tokio::spawn(Arc::clone(&validator).serve());
+        validator
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.connection.local_addr().expect("bound connection")
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    pub fn keypair(&self) -> &Arc<Keypair> {
+        &self.keypair
+    }
+
+    pub fn contact_info(&self) -> ContactInfo {
+        ContactInfo::new(self.pubkey(), timestamp(), self.shred_version, self.addr())
+    }
+
+    // Values returned to pull requests that don't already have them.
+    pub fn add_value(&self, value: GossipValue) {
+        self.values.lock().unwrap().push(value);
+    }
+
+    pub fn num_pings(&self) -> u64 {
+        self.num_pings.load(Ordering::Relaxed)
+    }
+
+    pub fn num_pull_requests(&self) -> u64 {
+        self.num_pull_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn num_push_messages(&self) -> u64 {
+        self.num_push_messages.load(Ordering::Relaxed)
+    }
+
+    pub async fn ping(&self, to: SocketAddr) {
+        let Ok(ping) = Ping::rand(&self.keypair) else {
+            return;
+        };
+        if let Ok(message) = serialize(&Protocol::PingMessage(ping)) {
+            self.send(vec![message], to).await;
+        }
+    }
+
+    pub async fn push(&self, to: SocketAddr, values: Vec<GossipValue>) -> Result<(), PushErrors> {
+        let messages = create_push_messages(self.pubkey(), values)?;
+        self.send(messages, to).await;
+        Ok(())
+    }
+
+    async fn send(&self, messages: Vec<Vec<u8>>, to: SocketAddr) {
+        for message in messages {
+            if self.connection.tx_out.send((message, to)).await.is_err() {
+                log::warn!("Failed to queue message to:{:?}", to);
+            }
+        }
+    }
+
+    async fn serve(self: Arc<Self>) {
+        let mut receive_channel = self.connection.rx_in.lock().await;
+        while let Some(batch) = receive_channel.recv().await {
+            for (message, from) in batch {
+                let Ok(protocol) = deserialize::<Protocol>(&message) else {
+                    continue;
+                };
+                match protocol {
+                    Protocol::PingMessage(ping) => {
+                        self.num_pings.fetch_add(1, Ordering::Relaxed);
+                        let _ = Ping::process(
+                            ping,
+                            from,
+                            self.connection.tx_out.clone(),
+                            Arc::clone(&self.keypair),
+                        )
+                        .await;
+                    }
+                    Protocol::PullRequest(filter, _) => {
+                        self.num_pull_requests.fetch_add(1, Ordering::Relaxed);
+                        let values: Vec<GossipValue> = self
+                            .pull_values()
+                            .into_iter()
+                            .filter(|value| {
+                                hash_value(value).is_ok_and(|hash| {
+                                    filter.test_mask(&hash) && !filter.contains(&hash)
+                                })
+                            })
+                            .collect();
+                        if let Ok(responses) = create_pull_responses(self.pubkey(), values) {
+                            self.send(responses, from).await;
+                        }
+                    }
+                    Protocol::PushMessage(..) => {
+                        self.num_push_messages.fetch_add(1, Ordering::Relaxed);
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn pull_values(&self) -> Vec<GossipValue> {
+        let contact_info = GossipData::ContactInfo(self.contact_info());
+        let mut values = vec![GossipValue::new_signed(contact_info, &self.keypair)];
+        values.extend(self.values.lock().unwrap().iter().cloned());
+        values
+    }
+}
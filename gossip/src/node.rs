@@ -0,0 +1,541 @@
+use {
+    crate::{
+        connection::Connection,
+        error::{ErrorStats, GossipError},
+        events::{EventFilter, EventReceiver, GossipEvent, EVENT_CHANNEL_CAPACITY},
+        gossip_data::gossip_data::GossipLabel,
+        gossip_data::{
+            contact_info::ContactInfo,
+            gossip_data::{GossipData, GossipValue, NodeInstance},
+        },
+        gossip_table::{GossipEntry, GossipTable},
+        handler::{GossipHandler, HandlerAction},
+        metrics::NodeMetrics,
+        peer_sampler::{gossip_peers, Peer, PeerSampler},
+        ping_pong::{Ping, PingCache, PingRateLimiter},
+        process_message::process_message,
+        protocol::Protocol,
+        prune::{PruneData, MAX_PRUNE_DATA_NODES},
+        pull_request::{build_data_filters, create_pull_requests, PullRequestErrors},
+        push_active_set::PushActiveSet,
+        push_message::{create_push_messages, PushQueue, PUSH_FANOUT},
+        received_cache::ReceivedCache,
+        stakes::StakeSnapshot,
+        verify::VerifyStats,
+        wallclock::{SkewLog, WallclockWindow},
+    },
+    bincode::serialize,
+    solana_sdk::{
+        hash::Hash, pubkey::Pubkey, signature::Keypair, signer::Signer, timing::timestamp,
+    },
+    std::{
+        collections::HashMap,
+        net::SocketAddr,
+        num::NonZero,
+        sync::{atomic::Ordering, Arc, Mutex, RwLock},
+        time::Duration,
+    },
+    tokio::{sync::broadcast, time::Instant},
+};
+
+const GOSSIP_PING_CACHE_TTL: Duration = Duration::from_secs(1280);
+const GOSSIP_PING_CACHE_RATE_LIMIT_DELAY: Duration = Duration::from_secs(1280 / 64);
+const GOSSIP_PING_CACHE_CAPACITY: usize = 126_976;
+const DEFAULT_PULL_INTERVAL: Duration = Duration::from_millis(500);
+// Validators drop unstaked values after 15s without an update.
+const DEFAULT_PUSH_INTERVAL: Duration = Duration::from_millis(7_500);
+const DEFAULT_ROTATE_INTERVAL: Duration = Duration::from_millis(7_500);
+const DEFAULT_PURGE_INTERVAL: Duration = Duration::from_secs(5);
+// Well past the 15s validators give unstaked values, a spy only hears about
+// most nodes through pull responses.
+const DEFAULT_VALUE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+pub struct NodeConfig {
+    pub shred_version: u16,
+    pub wallclock_window: WallclockWindow,
+    // Address advertised in our contact info, defaults to the bound socket.
+    pub gossip_addr: Option<SocketAddr>,
+    pub entrypoints: Vec<SocketAddr>,
+    pub pull_interval: Duration,
+    pub push_interval: Duration,
+    pub rotate_interval: Duration,
+    pub purge_interval: Duration,
+    // Values not updated for this long are dropped from the table.
+    pub value_timeout: Duration,
+    // Forward values received through push to our own active set.
+    pub relay: bool,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            shred_version: 0,
+            wallclock_window: WallclockWindow::default(),
+            gossip_addr: None,
+            entrypoints: vec![],
+            pull_interval: DEFAULT_PULL_INTERVAL,
+            push_interval: DEFAULT_PUSH_INTERVAL,
+            rotate_interval: DEFAULT_ROTATE_INTERVAL,
+            purge_interval: DEFAULT_PURGE_INTERVAL,
+            value_timeout: DEFAULT_VALUE_TIMEOUT,
+            relay: false,
+        }
+    }
+}
+
+// Who we are to the cluster, swapped as a whole on rotation.
+struct Identity {
+    keypair: Arc<Keypair>,
+    contact_info: ContactInfo,
+    instance: NodeInstance,
+}
+
+impl Identity {
+    fn new(keypair: Keypair, shred_version: u16, gossip_addr: SocketAddr) -> Self {
+        let now = timestamp();
+        Self {
+            contact_info: ContactInfo::new(keypair.pubkey(), now, shred_version, gossip_addr),
+            instance: NodeInstance::new(&mut rand::thread_rng(), keypair.pubkey(), now),
+            keypair: Arc::new(keypair),
+        }
+    }
+}
+
+pub struct GossipNode {
+    identity: RwLock<Identity>,
+    gossip_addr: SocketAddr,
+    pub connection: Arc<Connection>,
+    pub config: NodeConfig,
+    pub table: RwLock<GossipTable>,
+    pub ping_cache: Mutex<PingCache>,
+    pub ping_limiter: Mutex<PingRateLimiter>,
+    pub active_set: Mutex<PushActiveSet>,
+    pub push_queue: Mutex<PushQueue>,
+    pub received_cache: Mutex<ReceivedCache>,
+    pub stakes: RwLock<StakeSnapshot>,
+    pub skew_log: Mutex<SkewLog>,
+    pub verify_stats: VerifyStats,
+    pub errors: ErrorStats,
+    pub metrics: NodeMetrics,
+    handlers: RwLock<Vec<Arc<dyn GossipHandler>>>,
+    events: broadcast::Sender<GossipEvent>,
+}
+
+impl GossipNode {
+    pub fn new(connection: Arc<Connection>, keypair: Keypair, config: NodeConfig) -> Arc<Self> {
+        let ping_cache = PingCache::new(
+            GOSSIP_PING_CACHE_TTL,
+            GOSSIP_PING_CACHE_RATE_LIMIT_DELAY,
+            NonZero::new(GOSSIP_PING_CACHE_CAPACITY).unwrap(),
+        );
+
+        let gossip_addr = config
+            .gossip_addr
+            .unwrap_or_else(|| connection.local_addr().expect("socket has a local address"));
+        let identity = Identity::new(keypair, config.shred_version, gossip_addr);
+
+        Arc::new(Self {
+            identity: RwLock::new(identity),
+            gossip_addr,
+            connection,
+            config,
+            table: RwLock::default(),
+            ping_cache: Mutex::new(ping_cache),
+            ping_limiter: Mutex::default(),
+            active_set: Mutex::default(),
+            push_queue: Mutex::default(),
+            received_cache: Mutex::default(),
+            stakes: RwLock::default(),
+            skew_log: Mutex::default(),
+            verify_stats: VerifyStats::default(),
+            errors: ErrorStats::default(),
+            metrics: NodeMetrics::default(),
+            handlers: RwLock::default(),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        })
+    }
+
+    // Starts the connection, message processing and every gossip loop.
+    pub fn start(self: &Arc<Self>) {
+        self.start_processing();
+        self.start_pulling();
+        self.start_pushing();
+        self.start_rotating();
+        self.start_purging();
+    }
+
+    // Only handles incoming messages, without gossiping on our own. Enough
+    // to replay a capture.
+    pub fn start_processing(self: &Arc<Self>) {
+        self.connection.start_sending();
+        self.connection.start_receiving();
+        tokio::spawn(process_message(Arc::clone(self)));
+    }
+
+    pub fn keypair(&self) -> Arc<Keypair> {
+        Arc::clone(&self.identity.read().unwrap().keypair)
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.identity.read().unwrap().keypair.pubkey()
+    }
+
+    pub fn contact_info(&self) -> ContactInfo {
+        let mut contact_info = self.identity.read().unwrap().contact_info.clone();
+        contact_info.set_wallclock(timestamp());
+        contact_info
+    }
+
+    pub fn instance(&self) -> NodeInstance {
+        self.identity.read().unwrap().instance.clone()
+    }
+
+    // Carries on under a new pubkey, with a fresh contact info and node
+    // instance. Peers see a node they have to ping again before trusting it.
+    // Our values under the old pubkey are dropped, or we would take our old
+    // self for a peer.
+    pub fn set_keypair(&self, keypair: Keypair) {
+        let identity = Identity::new(keypair, self.config.shred_version, self.gossip_addr);
+        log::info!("Rotated identity to {}", identity.keypair.pubkey());
+        let old = std::mem::replace(&mut *self.identity.write().unwrap(), identity);
+        self.table
+            .write()
+            .unwrap()
+            .remove_pubkey(&old.keypair.pubkey());
+    }
+
+    pub fn report(&self, error: GossipError) {
+        self.errors.report(&error);
+    }
+
+    // Handlers run in the order they were added, the first `Drop` wins.
+    pub fn add_handler(&self, handler: Arc<dyn GossipHandler>) {
+        self.handlers.write().unwrap().push(handler);
+    }
+
+    pub fn handle_message(&self, from: SocketAddr, protocol: &Protocol) -> HandlerAction {
+        let handlers = self.handlers.read().unwrap();
+        let dropped = handlers.iter().any(|handler| {
+            let action = match protocol {
+                Protocol::PullRequest(filter, value) => {
+                    handler.on_pull_request(from, filter, value)
+                }
+                Protocol::PullResponse(sender, values) => {
+                    handler.on_pull_response(from, sender, values)
+                }
+                Protocol::PushMessage(sender, values) => {
+                    handler.on_push_message(from, sender, values)
+                }
+                Protocol::PruneMessage(sender, data) => {
+                    handler.on_prune_message(from, sender, data)
+                }
+                Protocol::PingMessage(ping) => handler.on_ping(from, ping),
+                Protocol::PongMessage(pong) => handler.on_pong(from, pong),
+            };
+            action == HandlerAction::Drop
+        });
+        if dropped {
+            HandlerAction::Drop
+        } else {
+            HandlerAction::Continue
+        }
+    }
+
+    pub fn handle_value(&self, from: SocketAddr, value: &GossipValue) -> HandlerAction {
+        let handlers = self.handlers.read().unwrap();
+        if handlers
+            .iter()
+            .any(|handler| handler.on_value(from, value) == HandlerAction::Drop)
+        {
+            HandlerAction::Drop
+        } else {
+            HandlerAction::Continue
+        }
+    }
+
+    pub fn has_handlers(&self) -> bool {
+        !self.handlers.read().unwrap().is_empty()
+    }
+
+    pub fn accepted(&self, entries: &[GossipEntry]) {
+        let handlers = self.handlers.read().unwrap();
+        for entry in entries {
+            for handler in handlers.iter() {
+                handler.on_accepted(entry);
+            }
+        }
+    }
+
+    pub fn subscribe(&self) -> EventReceiver {
+        self.subscribe_with(EventFilter::default())
+    }
+
+    pub fn subscribe_with(&self, filter: EventFilter) -> EventReceiver {
+        EventReceiver::new(self.events.subscribe(), filter)
+    }
+
+    pub fn emit(&self, events: Vec<GossipEvent>) {
+        for event in events {
+            // Fails only when nobody is subscribed.
+            let _ = self.events.send(event);
+        }
+    }
+
+    pub fn set_stakes(&self, stakes: StakeSnapshot) {
+        *self.stakes.write().unwrap() = stakes;
+    }
+
+    pub async fn select_pull_peers(&self, num: usize) -> Vec<Peer> {
+        self.sample_peers(num).await
+    }
+
+    async fn sample_peers(&self, num: usize) -> Vec<Peer> {
+        let keypair = self.keypair();
+        let (peers, pings) = {
+            let candidates = gossip_peers(
+                &self.table.read().unwrap(),
+                &self.pubkey(),
+                self.config.shred_version,
+            );
+            let stakes = self.stakes.read().unwrap();
+            let mut ping_cache = self.ping_cache.lock().unwrap();
+            PeerSampler::new(&mut ping_cache, &stakes).sample(
+                &mut rand::thread_rng(),
+                candidates,
+                num,
+                // The tokio clock, so tests can move ping timeouts forward.
+                Instant::now().into_std(),
+                || Ping::rand(&keypair).ok(),
+            )
+        };
+
+        for (addr, ping) in pings {
+            let Ok(message) = serialize(&Protocol::PingMessage(ping)) else {
+                continue;
+            };
+            if self.connection.tx_out.send((message, addr)).await.is_err() {
+                self.report(GossipError::Send(addr));
+                continue;
+            }
+            self.metrics.pings_sent.fetch_add(1, Ordering::Relaxed);
+        }
+        peers
+    }
+
+    // Builds one filter per mask from the table and sends each to a sampled
+    // peer, falling back to the entrypoints while no peer is usable yet.
+    pub async fn pull_round(&self) -> Result<usize, PullRequestErrors> {
+        let start = Instant::now();
+        let contact_info = self.contact_info();
+        let hashes: Vec<Hash> = self
+            .table
+            .read()
+            .unwrap()
+            .entries()
+            .map(|entry| entry.hash)
+            .collect();
+        let filters = build_data_filters(&contact_info, &hashes);
+
+        let peers = self.select_pull_peers(filters.len()).await;
+        let addrs: Vec<SocketAddr> = if peers.is_empty() {
+            self.config.entrypoints.clone()
+        } else {
+            peers.iter().map(|peer| peer.gossip).collect()
+        };
+
+        let requests = create_pull_requests(contact_info, filters, &addrs, &self.keypair())?;
+        let num_requests = requests.len();
+        for (request, addr) in requests {
+            if self.connection.tx_out.send((request, addr)).await.is_err() {
+                self.report(GossipError::Send(addr));
+            }
+        }
+        self.metrics.pull_round.observe(start.elapsed());
+        Ok(num_requests)
+    }
+
+    pub fn start_pulling(self: &Arc<Self>) {
+        let node = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(node.config.pull_interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = node.pull_round().await {
+                    node.report(e.into());
+                }
+            }
+        });
+    }
+
+    // Re-signs our contact info and node instance with a fresh wallclock, so
+    // other nodes keep us in their tables.
+    pub fn own_values(&self) -> Vec<GossipValue> {
+        let (keypair, instance) = {
+            let identity = self.identity.read().unwrap();
+            (Arc::clone(&identity.keypair), identity.instance.clone())
+        };
+        let contact_info = self.contact_info();
+        let now = contact_info.wallclock();
+        vec![
+            GossipValue::new_signed(GossipData::ContactInfo(contact_info), &keypair),
+            GossipValue::new_signed(
+                GossipData::NodeInstance(instance.with_wallclock(now)),
+                &keypair,
+            ),
+        ]
+    }
+
+    pub async fn rotate_active_set(&self) {
+        let candidates = self.sample_peers(usize::MAX).await;
+        self.active_set.lock().unwrap().rotate(candidates);
+    }
+
+    pub fn start_rotating(self: &Arc<Self>) {
+        let node = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(node.config.rotate_interval);
+            loop {
+                interval.tick().await;
+                node.rotate_active_set().await;
+            }
+        });
+    }
+
+    // Pushes our own values plus anything queued for relay, each one to the
+    // active set peers that haven't pruned its origin. A peer whose messages
+    // can't be built is reported and skipped.
+    pub async fn push_round(&self) -> usize {
+        let mut values = self.own_values();
+        {
+            let now = timestamp();
+            let mut table = self.table.write().unwrap();
+            for value in &values {
+                let _ = table.insert(value.clone(), now);
+            }
+        }
+        values.append(&mut self.push_queue.lock().unwrap().drain());
+
+        if self.active_set.lock().unwrap().is_empty() {
+            self.rotate_active_set().await;
+        }
+
+        let mut peer_values: HashMap<SocketAddr, Vec<GossipValue>> = HashMap::new();
+        {
+            let active_set = self.active_set.lock().unwrap();
+            for value in values {
+                let origin = value.pubkey();
+                for peer in active_set.get_nodes(&origin, PUSH_FANOUT) {
+                    peer_values
+                        .entry(peer.gossip)
+                        .or_default()
+                        .push(value.clone());
+                }
+            }
+        }
+
+        let num_peers = peer_values.len();
+        for (addr, values) in peer_values {
+            let messages = match create_push_messages(self.pubkey(), values) {
+                Ok(messages) => messages,
+                Err(err) => {
+                    self.report(err.into());
+                    continue;
+                }
+            };
+            for message in messages {
+                if self.connection.tx_out.send((message, addr)).await.is_err() {
+                    self.report(GossipError::Send(addr));
+                }
+            }
+        }
+        num_peers
+    }
+
+    pub fn start_pushing(self: &Arc<Self>) {
+        let node = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(node.config.push_interval);
+            loop {
+                interval.tick().await;
+                node.push_round().await;
+            }
+        });
+    }
+
+    // Asks redundant push senders of `origins` to stop forwarding them to us.
+    pub async fn send_prunes(&self, origins: impl IntoIterator<Item = Pubkey>) {
+        let keypair = self.keypair();
+        let pubkey = keypair.pubkey();
+        let mut prunes: HashMap<Pubkey, Vec<Pubkey>> = HashMap::new();
+        {
+            let stakes = self.stakes.read().unwrap();
+            let mut received_cache = self.received_cache.lock().unwrap();
+            for origin in origins {
+                for sender in received_cache.prune(&pubkey, &origin, &stakes) {
+                    prunes.entry(sender).or_default().push(origin);
+                }
+            }
+        }
+        if prunes.is_empty() {
+            return;
+        }
+
+        let mut messages = vec![];
+        {
+            let table = self.table.read().unwrap();
+            let now = timestamp();
+            for (sender, origins) in prunes {
+                let Some(entry) = table.get(&GossipLabel::ContactInfo(sender)) else {
+                    continue;
+                };
+                let GossipData::ContactInfo(contact_info) = &entry.value.data else {
+                    continue;
+                };
+                let Some(addr) = contact_info.gossip() else {
+                    continue;
+                };
+                for chunk in origins.chunks(MAX_PRUNE_DATA_NODES) {
+                    let data = PruneData::new_signed(&keypair, chunk.to_vec(), sender, now);
+                    if let Ok(message) = serialize(&Protocol::PruneMessage(pubkey, data)) {
+                        messages.push((message, addr));
+                    }
+                }
+            }
+        }
+        for (message, addr) in messages {
+            if self.connection.tx_out.send((message, addr)).await.is_err() {
+                self.report(GossipError::Send(addr));
+            }
+        }
+    }
+
+    pub fn purge_round(&self) -> usize {
+        let timeout = self.config.value_timeout.as_millis() as u64;
+        let purged = self
+            .table
+            .write()
+            .unwrap()
+            .purge(&self.pubkey(), timestamp(), timeout);
+        // Mirrors NodeJoined, which only a ContactInfo triggers. There is at
+        // most one per pubkey, and once purged the node has none left.
+        let events = purged
+            .iter()
+            .filter(|value| matches!(value.data, GossipData::ContactInfo(_)))
+            .map(|value| GossipEvent::NodeLeft(value.pubkey()))
+            .collect();
+        self.emit(events);
+        purged.len()
+    }
+
+    pub fn start_purging(self: &Arc<Self>) {
+        let node = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(node.config.purge_interval);
+            loop {
+                interval.tick().await;
+                node.purge_round();
+            }
+        });
+    }
+}
@@ -0,0 +1,223 @@
+use {
+    crate::{
+        gossip_data::gossip_data::GossipData,
+        gossip_table::GossipTable,
+        ping_pong::{Ping, PingCache},
+        stakes::StakeSnapshot,
+    },
+    rand::{seq::SliceRandom, Rng},
+    solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey},
+    std::{net::SocketAddr, time::Instant},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Peer {
+    pub pubkey: Pubkey,
+    pub gossip: SocketAddr,
+}
+
+// Every contact info in the table except our own, with a gossip socket and a
+// matching shred version. A shred version of 0 means we don't know the
+// cluster's yet, so every node is accepted.
+pub fn gossip_peers(table: &GossipTable, self_pubkey: &Pubkey, shred_version: u16) -> Vec<Peer> {
+    table
+        .entries()
+        .filter_map(|entry| match &entry.value.data {
+            GossipData::ContactInfo(contact_info) => Some(contact_info),
+            _ => None,
+        })
+        .filter(|contact_info| contact_info.pubkey() != self_pubkey)
+        .filter(|contact_info| shred_version == 0 || contact_info.shred_version() == shred_version)
+        .filter_map(|contact_info| {
+            Some(Peer {
+                pubkey: *contact_info.pubkey(),
+                gossip: contact_info.gossip()?,
+            })
+        })
+        .collect()
+}
+
+// Same buckets as the validator's pull weights: log2 of the stake in SOL,
+// squared, so large stakes are preferred without starving small nodes.
+pub fn pull_weight(stake: u64) -> u64 {
+    let stake = stake / LAMPORTS_PER_SOL;
+    let bucket = u64::from(u64::BITS - stake.leading_zeros());
+    (bucket + 1).pow(2)
+}
+
+pub struct PeerSampler<'a> {
+    ping_cache: &'a mut PingCache,
+    stakes: &'a StakeSnapshot,
+}
+
+impl<'a> PeerSampler<'a> {
+    pub fn new(ping_cache: &'a mut PingCache, stakes: &'a StakeSnapshot) -> Self {
+        Self { ping_cache, stakes }
+    }
+
+    // Returns up to `num` peers with a verified pong, plus the pings to send
+    // to the candidates that still need one.
+    pub fn sample<R, F>(
+        &mut self,
+        rng: &mut R,
+        peers: Vec<Peer>,
+        num: usize,
+        now: Instant,
+        mut pingf: F,
+    ) -> (Vec<Peer>, Vec<(SocketAddr, Ping)>)
+    where
+        R: Rng,
+        F: FnMut() -> Option<Ping>,
+    {
+        let mut pings = vec![];
+        let peers: Vec<Peer> = peers
+            .into_iter()
+            .filter(|peer| {
+                let (check, ping) =
+                    self.ping_cache
+                        .check(now, (peer.pubkey, peer.gossip), &mut pingf);
+                if let Some(ping) = ping {
+                    pings.push((peer.gossip, ping));
+                }
+                check
+            })
+            .collect();
+
+        let selected = if self.stakes.is_empty() {
+            peers.choose_multiple(rng, num).copied().collect()
+        } else {
+            peers
+                .choose_multiple_weighted(rng, num, |peer| {
+                    pull_weight(self.stakes.get(&peer.pubkey)) as f64
+                })
+                .map(|peers| peers.copied().collect())
+                .unwrap_or_default()
+        };
+        (selected, pings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            gossip_data::{contact_info::ContactInfo, gossip_data::GossipValue},
+            ping_pong::Pong,
+        },
+        solana_sdk::{signature::Keypair, signer::Signer, timing::timestamp},
+        std::{collections::HashMap, num::NonZero, time::Duration},
+    };
+
+    fn new_ping_cache() -> PingCache {
+        PingCache::new(
+            Duration::from_secs(1280),
+            Duration::from_secs(20),
+            NonZero::new(128).unwrap(),
+        )
+    }
+
+    fn new_peers(num: u16) -> Vec<(Keypair, Peer)> {
+        (0..num)
+            .map(|i| {
+                let keypair = Keypair::new();
+                let peer = Peer {
+                    pubkey: keypair.pubkey(),
+                    gossip: SocketAddr::from(([127, 0, 0, 1], 8000 + i)),
+                };
+                (keypair, peer)
+            })
+            .collect()
+    }
+
+    fn pong_all(ping_cache: &mut PingCache, peers: &[(Keypair, Peer)], now: Instant) {
+        let ping_keypair = Keypair::new();
+        for (keypair, peer) in peers {
+            let (_, ping) = ping_cache.check(now, (peer.pubkey, peer.gossip), || {
+                Ping::rand(&ping_keypair).ok()
+            });
+            let pong = Pong::new(&ping.unwrap(), keypair).unwrap();
+            assert!(ping_cache.add(&pong, peer.gossip, now).is_some());
+        }
+    }
+
+    #[test]
+    fn test_gossip_peers_skips_wrong_shred_version() {
+        let self_keypair = Keypair::new();
+        let mut table = GossipTable::default();
+        let mut expected = vec![];
+        for (shred_version, keypair) in [
+            (1, Keypair::new()),
+            (2, Keypair::new()),
+            (1, self_keypair.insecure_clone()),
+        ] {
+            let gossip = SocketAddr::from(([127, 0, 0, 1], 8001));
+            let contact_info =
+                ContactInfo::new(keypair.pubkey(), timestamp(), shred_version, gossip);
+            let value = GossipValue::new_signed(GossipData::ContactInfo(contact_info), &keypair);
+            table.insert(value, timestamp()).unwrap();
+            if shred_version == 1 && keypair.pubkey() != self_keypair.pubkey() {
+                expected.push(Peer {
+                    pubkey: keypair.pubkey(),
+                    gossip,
+                });
+            }
+        }
+
+        assert_eq!(gossip_peers(&table, &self_keypair.pubkey(), 1), expected);
+        assert_eq!(gossip_peers(&table, &self_keypair.pubkey(), 0).len(), 2);
+    }
+
+    #[test]
+    fn test_sample_requires_pong() {
+        let mut rng = rand::thread_rng();
+        let now = Instant::now();
+        let mut ping_cache = new_ping_cache();
+        let stakes = StakeSnapshot::default();
+        let ping_keypair = Keypair::new();
+        let peers = new_peers(4);
+        pong_all(&mut ping_cache, &peers[..2], now);
+        let peers: Vec<Peer> = peers.into_iter().map(|(_, peer)| peer).collect();
+
+        let mut sampler = PeerSampler::new(&mut ping_cache, &stakes);
+        let (selected, pings) = sampler.sample(&mut rng, peers.clone(), 10, now, || {
+            Ping::rand(&ping_keypair).ok()
+        });
+
+        assert_eq!(selected.len(), 2);
+        assert!(selected.iter().all(|peer| peers[..2].contains(peer)));
+        let pinged: Vec<SocketAddr> = pings.iter().map(|(addr, _)| *addr).collect();
+        assert_eq!(pinged, vec![peers[2].gossip, peers[3].gossip]);
+    }
+
+    #[test]
+    fn test_sample_prefers_staked_peers() {
+        let mut rng = rand::thread_rng();
+        let now = Instant::now();
+        let mut ping_cache = new_ping_cache();
+        let peers = new_peers(2);
+        pong_all(&mut ping_cache, &peers, now);
+        let peers: Vec<Peer> = peers.into_iter().map(|(_, peer)| peer).collect();
+        let stakes = StakeSnapshot::new(HashMap::from([(
+            peers[0].pubkey,
+            1_000_000 * LAMPORTS_PER_SOL,
+        )]));
+
+        let mut sampler = PeerSampler::new(&mut ping_cache, &stakes);
+        let hits = (0..1000)
+            .filter(|_| {
+                let (selected, _) = sampler.sample(&mut rng, peers.clone(), 1, now, || None);
+                selected == vec![peers[0]]
+            })
+            .count();
+
+        assert!(hits > 900, "staked peer selected only {hits} times");
+    }
+
+    #[test]
+    fn test_pull_weight() {
+        assert_eq!(pull_weight(0), 1);
+        assert_eq!(pull_weight(LAMPORTS_PER_SOL), 4);
+        assert!(pull_weight(1_000_000 * LAMPORTS_PER_SOL) > pull_weight(1_000 * LAMPORTS_PER_SOL));
+    }
+}
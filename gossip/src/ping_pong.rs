@@ -1,4 +1,5 @@
 use {
+    crate::protocol::Protocol,
     bincode::{serialize, Error},
     lru::LruCache,
     rand::Rng,
@@ -54,15 +55,31 @@ impl Ping {
         Self::new(random_bytes, keypair)
     }
 
+    pub fn from(&self) -> &Pubkey {
+        &self.from
+    }
+
+    pub fn verify(&self) -> bool {
+        serialize(&self.token)
+            .map(|token| self.signature.verify(self.from.as_ref(), &token))
+            .unwrap_or(false)
+    }
+
+    // The hash a valid pong to this ping carries.
+    pub fn pong_hash(&self) -> Result<Hash, Error> {
+        let token = serialize(&self.token)?;
+        Ok(hash::hashv(&[PING_PONG_HASH_PREFIX, &token]))
+    }
+
     pub async fn process(
         ping: Self,
         from: SocketAddr,
         tx_out: Sender<(Vec<u8>, SocketAddr)>,
         keypair: Arc<Keypair>,
-    ) -> Result<(), PingPongErros> {
+    ) -> Result<(), PingPongErrors> {
         let pong = match Pong::new(&ping, &keypair) {
             Ok(p) => p,
-            Err(_) => return Err(PingPongErros::FailedToCreatePong),
+            Err(_) => return Err(PingPongErrors::FailedToCreatePong),
         };
 
         pong.send(from, tx_out).await?;
@@ -73,8 +90,7 @@ impl Ping {
 
 impl Pong {
     pub fn new(ping: &Ping, keypair: &Keypair) -> Result<Self, Error> {
-        let token = serialize(&ping.token)?;
-        let hash = hash::hashv(&[PING_PONG_HASH_PREFIX, &token]);
+        let hash = ping.pong_hash()?;
         let pong = Pong {
             from: keypair.pubkey(),
             hash,
@@ -87,25 +103,34 @@ impl Pong {
         &self.from
     }
 
+    pub fn hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    pub fn verify(&self) -> bool {
+        self.signature
+            .verify(self.from.as_ref(), self.hash.as_ref())
+    }
+
     async fn send(
-        &self,
+        self,
         addr: SocketAddr,
         tx_out: Sender<(Vec<u8>, SocketAddr)>,
-    ) -> Result<(), PingPongErros> {
-        let message = match serialize(self) {
+    ) -> Result<(), PingPongErrors> {
+        let message = match serialize(&Protocol::PongMessage(self)) {
             Ok(m) => m,
-            Err(_) => return Err(PingPongErros::FailedToSerealizePong),
+            Err(_) => return Err(PingPongErrors::FailedToSerealizePong),
         };
 
         match tx_out.send((message, addr)).await {
-            Ok(_) => return Ok(()),
-            Err(_) => return Err(PingPongErros::FailedToSendAPong),
-        };
+            Ok(_) => Ok(()),
+            Err(_) => Err(PingPongErrors::FailedToSendAPong),
+        }
     }
 }
 
 #[derive(Debug, Error)]
-pub enum PingPongErros {
+pub enum PingPongErrors {
     #[error("Failed to create pong to reply a ping")]
     FailedToCreatePong,
     #[error("Failed to serealize pong")]
@@ -114,12 +139,51 @@ pub enum PingPongErros {
     FailedToSendAPong,
 }
 
+// Replies to at most one ping per address per interval, so spoofed pings can't
+// turn us into a pong amplifier.
+pub const PING_REPLY_INTERVAL: Duration = Duration::from_secs(1);
+const PING_RATE_LIMITER_CAPACITY: usize = 65_536;
+
+pub struct PingRateLimiter {
+    interval: Duration,
+    last_reply: LruCache<SocketAddr, Instant>,
+}
+
+impl Default for PingRateLimiter {
+    fn default() -> Self {
+        Self::new(
+            PING_REPLY_INTERVAL,
+            NonZero::new(PING_RATE_LIMITER_CAPACITY).unwrap(),
+        )
+    }
+}
+
+impl PingRateLimiter {
+    pub fn new(interval: Duration, cap: NonZero<usize>) -> Self {
+        Self {
+            interval,
+            last_reply: LruCache::new(cap),
+        }
+    }
+
+    pub fn allow(&mut self, addr: SocketAddr, now: Instant) -> bool {
+        match self.last_reply.peek(&addr) {
+            Some(last) if now.saturating_duration_since(*last) < self.interval => false,
+            _ => {
+                self.last_reply.put(addr, now);
+                true
+            }
+        }
+    }
+}
+
 pub struct PingCache {
     ttl: Duration,
     rate_limit_delay: Duration,
     pings: LruCache<(Pubkey, SocketAddr), Instant>,
     pongs: LruCache<(Pubkey, SocketAddr), Instant>,
-    pending_cache: LruCache<Hash, (Pubkey, SocketAddr)>,
+    // Pinged node and send time per expected pong hash.
+    pending_cache: LruCache<Hash, ((Pubkey, SocketAddr), Instant)>,
 }
 
 impl PingCache {
@@ -133,16 +197,19 @@ impl PingCache {
         }
     }
 
-    pub fn add(&mut self, pong: &Pong, socket: SocketAddr, now: Instant) -> bool {
+    // Returns the round trip time if the pong answers a pending ping, timed
+    // from that very ping rather than the latest one sent to the node.
+    pub fn add(&mut self, pong: &Pong, socket: SocketAddr, now: Instant) -> Option<Duration> {
         let node = (pong.from, socket);
         match self.pending_cache.peek(&pong.hash) {
-            Some(value) if *value == node => {
+            Some((value, sent)) if *value == node => {
+                let rtt = now.saturating_duration_since(*sent);
                 self.pings.pop(&node);
                 self.pongs.put(node, now);
                 self.pending_cache.pop(&pong.hash);
-                true
+                Some(rtt)
             }
-            _ => false,
+            _ => None,
         }
     }
 
@@ -159,9 +226,8 @@ impl PingCache {
             Some(t) if now.saturating_duration_since(*t) < self.rate_limit_delay => None,
             _ => {
                 let ping = pingf()?;
-                let token = serialize(&ping.token).ok()?;
-                let hash = hash::hashv(&[PING_PONG_HASH_PREFIX, &token]);
-                self.pending_cache.put(hash, node);
+                let hash = ping.pong_hash().ok()?;
+                self.pending_cache.put(hash, (node, now));
                 self.pings.put(node, now);
                 Some(ping)
             }
@@ -200,6 +266,42 @@ impl PingCache {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_verify_pong() {
+        let keypair = Keypair::new();
+        let ping = Ping::rand(&Keypair::new()).expect("Failed to create ping");
+        let mut pong = Pong::new(&ping, &keypair).expect("Failed to create pong");
+        assert!(ping.verify());
+        assert!(pong.verify());
+
+        pong.from = Keypair::new().pubkey();
+        assert!(!pong.verify());
+    }
+
+    #[tokio::test]
+    async fn test_process_ping_replies_with_pong_message() {
+        let keypair = Arc::new(Keypair::new());
+        let ping = Ping::rand(&Keypair::new()).expect("Failed to create ping");
+        let hash = ping.pong_hash().unwrap();
+        let from = SocketAddr::from(([127, 0, 0, 1], 8001));
+        let (tx_out, mut rx_out) = tokio::sync::mpsc::channel(1);
+        Ping::process(ping, from, tx_out, keypair.clone())
+            .await
+            .unwrap();
+
+        // Sent as a protocol message, not a bare pong.
+        let (message, addr) = rx_out.recv().await.unwrap();
+        assert_eq!(addr, from);
+        match bincode::deserialize(&message).unwrap() {
+            Protocol::PongMessage(pong) => {
+                assert_eq!(pong.hash, hash);
+                assert_eq!(pong.from, keypair.pubkey());
+                assert!(pong.verify());
+            }
+            _ => panic!("expected a pong message"),
+        }
+    }
+
     #[test]
     fn test_create_pong() {
         let keypair = Keypair::new();
@@ -213,4 +315,43 @@ mod tests {
             "The pong hash does not match the expected hash"
         );
     }
+
+    #[test]
+    fn test_pong_rtt() {
+        let keypair = Keypair::new();
+        let peer = Keypair::new();
+        let node = (peer.pubkey(), SocketAddr::from(([127, 0, 0, 1], 8001)));
+        let mut cache = PingCache::new(
+            Duration::from_secs(60),
+            Duration::from_secs(1),
+            NonZero::new(16).unwrap(),
+        );
+        let now = Instant::now();
+        let (_, first) = cache.check(now, node, || Ping::rand(&keypair).ok());
+        let first = first.unwrap();
+        let later = now + Duration::from_secs(2);
+        let (_, second) = cache.check(later, node, || Ping::rand(&keypair).ok());
+        assert!(second.is_some());
+
+        // A late pong to the first ping is timed from the first ping.
+        let pong = Pong::new(&first, &peer).unwrap();
+        let arrived = later + Duration::from_millis(10);
+        assert_eq!(
+            cache.add(&pong, node.1, arrived),
+            Some(Duration::from_millis(2_010))
+        );
+        assert_eq!(cache.add(&pong, node.1, arrived), None);
+    }
+
+    #[test]
+    fn test_ping_rate_limiter() {
+        let mut limiter = PingRateLimiter::default();
+        let addr = SocketAddr::from(([127, 0, 0, 1], 8001));
+        let now = Instant::now();
+
+        assert!(limiter.allow(addr, now));
+        assert!(!limiter.allow(addr, now + PING_REPLY_INTERVAL / 2));
+        assert!(limiter.allow(SocketAddr::from(([127, 0, 0, 1], 8002)), now));
+        assert!(limiter.allow(addr, now + PING_REPLY_INTERVAL));
+    }
 }
@@ -1,38 +1,241 @@
 use {
-    crate::{connection::Connection, ping_pong::Ping, protocol::Protocol},
+    crate::{
+        connection::DropReason,
+        error::GossipError,
+        events::events_for,
+        gossip_data::{
+            gossip_data::{GossipData, GossipValue},
+            sanitize::Sanitize,
+        },
+        gossip_table::{hash_value, GossipTableErrors},
+        handler::HandlerAction,
+        node::GossipNode,
+        ping_pong::Ping,
+        protocol::Protocol,
+        verify::verify_values,
+        wallclock::{filter_wallclocks, ValueSource},
+    },
     bincode::deserialize,
-    solana_sdk::signature::Keypair,
-    std::sync::Arc,
+    solana_sdk::{pubkey::Pubkey, timing::timestamp},
+    std::{collections::HashSet, net::SocketAddr, sync::Arc},
+    tokio::time::Instant,
 };
 
-pub async fn process_message(connection: Arc<Connection>, keypair: Keypair) {
-    let mut receive_channel = connection.rx_in.lock().await;
-    let arc_keypair = Arc::new(keypair);
+pub async fn process_message(node: Arc<GossipNode>) {
+    let mut receive_channel = node.connection.rx_in.lock().await;
 
-    while let Some((message, from)) = receive_channel.recv().await {
-        let connection_clone = Arc::clone(&connection);
-        let arc_keypair_clone = Arc::clone(&arc_keypair);
-        tokio::spawn(async move {
-            let protocol: Result<Protocol, _> = deserialize(&message);
+    while let Some(batch) = receive_channel.recv().await {
+        for (message, from) in batch {
+            let node = Arc::clone(&node);
+            tokio::spawn(async move {
+                if let Err(e) = process_packet(&node, &message, from).await {
+                    node.report(e);
+                }
+            });
+        }
+    }
+}
+
+async fn process_packet(
+    node: &GossipNode,
+    message: &[u8],
+    from: SocketAddr,
+) -> Result<(), GossipError> {
+    let protocol: Protocol = deserialize(message).map_err(|e| {
+        node.connection.drops().record(DropReason::DecodeFailed);
+        GossipError::Decode {
+            from,
+            reason: e.to_string(),
+        }
+    })?;
+    if node.handle_message(from, &protocol) == HandlerAction::Drop {
+        return Err(GossipError::HandlerDropped(from));
+    }
+    match protocol {
+        Protocol::PullResponse(sender, values) => {
+            process_values(node, values, ValueSource::PullResponse, sender, from).await;
+        }
+        Protocol::PushMessage(sender, values) => {
+            let origins: HashSet<Pubkey> = values.iter().map(GossipValue::pubkey).collect();
+            process_values(node, values, ValueSource::Push, sender, from).await;
+            node.send_prunes(origins).await;
+        }
+        protocol => {
+            protocol
+                .sanitize()
+                .map_err(|error| GossipError::Sanitize { from, error })?;
             match protocol {
-                Ok(protocol) => match protocol {
-                    Protocol::PullResponse => (),
-                    Protocol::PushMessage => (),
-                    Protocol::PruneMessage => (),
-                    Protocol::PingMessage(ping) => {
-                        let _ = Ping::process(
-                            ping,
+                Protocol::PingMessage(ping) => {
+                    let now = Instant::now().into_std();
+                    if !node.ping_limiter.lock().unwrap().allow(from, now) {
+                        return Err(GossipError::RateLimited(from));
+                    }
+                    Ping::process(ping, from, node.connection.tx_out.clone(), node.keypair())
+                        .await?;
+                }
+                Protocol::PruneMessage(_, prune_data) => {
+                    if prune_data.destination != node.pubkey() {
+                        return Err(GossipError::PruneDestination {
+                            from,
+                            destination: prune_data.destination,
+                        });
+                    }
+                    if prune_data.is_expired(timestamp()) {
+                        return Err(GossipError::PruneExpired {
                             from,
-                            connection_clone.tx_out.clone(),
-                            arc_keypair_clone,
-                        )
-                        .await;
+                            wallclock: prune_data.wallclock,
+                        });
                     }
-                    Protocol::PongMessage(pong) => todo!(),
-                    _ => (),
-                },
-                Err(_) => (),
+                    if !prune_data.verify() {
+                        return Err(GossipError::Signature {
+                            from,
+                            origin: prune_data.pubkey,
+                        });
+                    }
+                    node.active_set
+                        .lock()
+                        .unwrap()
+                        .prune(&prune_data.pubkey, &prune_data.prunes);
+                }
+                Protocol::PongMessage(pong) => {
+                    if !pong.verify() {
+                        return Err(GossipError::Signature {
+                            from,
+                            origin: *pong.from(),
+                        });
+                    }
+                    let rtt =
+                        node.ping_cache
+                            .lock()
+                            .unwrap()
+                            .add(&pong, from, Instant::now().into_std());
+                    node.metrics.record_pong(rtt);
+                }
+                _ => (),
             }
-        });
+        }
     }
+    Ok(())
+}
+
+// Drops every value that fails a check, reporting why, and stores the rest.
+async fn process_values(
+    node: &GossipNode,
+    values: Vec<GossipValue>,
+    source: ValueSource,
+    sender: Pubkey,
+    from: SocketAddr,
+) {
+    let values = sanitize_values(node, values, from);
+    let values = check_shred_versions(node, values);
+    let (values, failed) = verify_values(values, &node.verify_stats).await;
+    for origin in failed {
+        node.report(GossipError::Signature { from, origin });
+    }
+    let now = timestamp();
+    let values = filter_wallclocks(
+        values,
+        source,
+        from,
+        &node.config.wallclock_window,
+        &mut node.skew_log.lock().unwrap(),
+        now,
+    );
+    let values: Vec<GossipValue> = values
+        .into_iter()
+        .filter(|value| match node.handle_value(from, value) {
+            HandlerAction::Continue => true,
+            HandlerAction::Drop => {
+                node.report(GossipError::HandlerDropped(from));
+                false
+            }
+        })
+        .collect();
+    if values.is_empty() {
+        return;
+    }
+    if source == ValueSource::Push {
+        let mut received_cache = node.received_cache.lock().unwrap();
+        for value in &values {
+            if let Ok(hash) = hash_value(value) {
+                received_cache.record(value.pubkey(), hash, sender);
+            }
+        }
+    }
+    let relay = node.config.relay && source == ValueSource::Push;
+    // Entries are only copied out of the table when a handler wants them.
+    let notify = node.has_handlers();
+    let mut inserted = vec![];
+    let mut accepted = vec![];
+    let mut events = vec![];
+    {
+        let mut table = node.table.write().unwrap();
+        for value in values {
+            let old = match table.insert(value.clone(), now) {
+                Ok(old) => old,
+                // Peers still serving a value keep it from being purged.
+                Err(GossipTableErrors::DuplicateValue) if source == ValueSource::PullResponse => {
+                    table.refresh(&value.label(), now);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            events.extend(events_for(&value, old.as_ref()));
+            if notify {
+                accepted.extend(table.get(&value.label()).cloned());
+            }
+            if relay {
+                inserted.push(value);
+            }
+        }
+    }
+    node.accepted(&accepted);
+    node.emit(events);
+    if !inserted.is_empty() {
+        node.push_queue.lock().unwrap().extend(inserted);
+    }
+}
+
+fn sanitize_values(
+    node: &GossipNode,
+    values: Vec<GossipValue>,
+    from: SocketAddr,
+) -> Vec<GossipValue> {
+    values
+        .into_iter()
+        .filter(|value| match value.sanitize() {
+            Ok(()) => true,
+            Err(error) => {
+                node.report(GossipError::Sanitize { from, error });
+                false
+            }
+        })
+        .collect()
+}
+
+// Contact infos from other clusters would only pollute the peer list.
+fn check_shred_versions(node: &GossipNode, values: Vec<GossipValue>) -> Vec<GossipValue> {
+    let expected = node.config.shred_version;
+    if expected == 0 {
+        return values;
+    }
+    values
+        .into_iter()
+        .filter(|value| {
+            let actual = match &value.data {
+                GossipData::ContactInfo(contact_info) => contact_info.shred_version(),
+                GossipData::LegacyContactInfo(contact_info) => contact_info.shred_version(),
+                _ => return true,
+            };
+            if actual == expected {
+                return true;
+            }
+            node.report(GossipError::ShredVersion {
+                origin: value.pubkey(),
+                expected,
+                actual,
+            });
+            false
+        })
+        .collect()
 }
@@ -1,17 +1,45 @@
 use {
     crate::{
-        gossip_data::{filter::DataFilter, gossip_data::GossipValue},
+        gossip_data::{
+            filter::DataFilter,
+            gossip_data::GossipValue,
+            sanitize::{Sanitize, SanitizeErrors},
+        },
         ping_pong::{Ping, Pong},
+        prune::PruneData,
     },
     serde::{Deserialize, Serialize},
+    solana_sdk::pubkey::Pubkey,
 };
 
 #[derive(Debug, Deserialize, Serialize)]
+#[allow(clippy::large_enum_variant)]
 pub enum Protocol {
     PullRequest(DataFilter, GossipValue),
-    PullResponse,
-    PushMessage,
-    PruneMessage,
+    PullResponse(Pubkey, Vec<GossipValue>),
+    PushMessage(Pubkey, Vec<GossipValue>),
+    PruneMessage(Pubkey, PruneData),
     PingMessage(Ping),
     PongMessage(Pong),
 }
+
+impl Sanitize for Protocol {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        match self {
+            Protocol::PullRequest(filter, value) => {
+                filter.sanitize()?;
+                value.sanitize()
+            }
+            Protocol::PullResponse(_, values) => values.sanitize(),
+            Protocol::PushMessage(_, values) => values.sanitize(),
+            Protocol::PruneMessage(from, data) => {
+                if *from != data.pubkey {
+                    return Err(SanitizeErrors::PruneSenderMismatch);
+                }
+                data.sanitize()
+            }
+            Protocol::PingMessage(_) => Ok(()),
+            Protocol::PongMessage(_) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use {
+    crate::gossip_data::sanitize::{sanitize_wallclock, Sanitize, SanitizeErrors},
+    bincode::serialize,
+    serde::{Deserialize, Serialize},
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+        signer::Signer,
+    },
+};
+
+const PRUNE_DATA_PREFIX: &[u8] = b"\xffSOLANA_PRUNE_DATA";
+// Keeps a PruneMessage within a single packet.
+pub const MAX_PRUNE_DATA_NODES: usize = 32;
+// Same as the validator's prune timeout, so a replayed prune is not applied again.
+pub const PRUNE_MSG_TIMEOUT_MS: u64 = 500;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PruneData {
+    pub pubkey: Pubkey,
+    pub prunes: Vec<Pubkey>,
+    pub signature: Signature,
+    pub destination: Pubkey,
+    pub wallclock: u64,
+}
+
+#[derive(Serialize)]
+struct SignDataWithPrefix<'a> {
+    prefix: &'a [u8],
+    pubkey: &'a Pubkey,
+    prunes: &'a [Pubkey],
+    destination: &'a Pubkey,
+    wallclock: u64,
+}
+
+#[derive(Serialize)]
+struct SignDataWithoutPrefix<'a> {
+    pubkey: &'a Pubkey,
+    prunes: &'a [Pubkey],
+    destination: &'a Pubkey,
+    wallclock: u64,
+}
+
+impl PruneData {
+    pub fn new_signed(
+        keypair: &Keypair,
+        prunes: Vec<Pubkey>,
+        destination: Pubkey,
+        wallclock: u64,
+    ) -> Self {
+        let mut prune_data = Self {
+            pubkey: keypair.pubkey(),
+            prunes,
+            signature: Signature::default(),
+            destination,
+            wallclock,
+        };
+        prune_data.signature = keypair.sign_message(&prune_data.signable_data_with_prefix());
+        prune_data
+    }
+
+    fn signable_data_with_prefix(&self) -> Vec<u8> {
+        serialize(&SignDataWithPrefix {
+            prefix: PRUNE_DATA_PREFIX,
+            pubkey: &self.pubkey,
+            prunes: &self.prunes,
+            destination: &self.destination,
+            wallclock: self.wallclock,
+        })
+        .expect("failed to serialize PruneData")
+    }
+
+    fn signable_data_without_prefix(&self) -> Vec<u8> {
+        serialize(&SignDataWithoutPrefix {
+            pubkey: &self.pubkey,
+            prunes: &self.prunes,
+            destination: &self.destination,
+            wallclock: self.wallclock,
+        })
+        .expect("failed to serialize PruneData")
+    }
+
+    // Validators still accept prunes signed before the prefix was added.
+    pub fn verify(&self) -> bool {
+        let pubkey = self.pubkey.as_ref();
+        self.signature
+            .verify(pubkey, &self.signable_data_with_prefix())
+            || self
+                .signature
+                .verify(pubkey, &self.signable_data_without_prefix())
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.wallclock.saturating_add(PRUNE_MSG_TIMEOUT_MS) < now
+    }
+}
+
+impl Sanitize for PruneData {
+    fn sanitize(&self) -> Result<(), SanitizeErrors> {
+        sanitize_wallclock(self.wallclock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::timing::timestamp};
+
+    #[test]
+    fn test_verify_prune_data() {
+        let keypair = Keypair::new();
+        let prunes = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut prune_data =
+            PruneData::new_signed(&keypair, prunes, Pubkey::new_unique(), timestamp());
+        assert!(prune_data.verify());
+
+        prune_data.signature = keypair.sign_message(&prune_data.signable_data_without_prefix());
+        assert!(prune_data.verify());
+
+        prune_data.destination = Pubkey::new_unique();
+        assert!(!prune_data.verify());
+    }
+
+    #[test]
+    fn test_prune_data_expires() {
+        let now = timestamp();
+        let prune_data = PruneData::new_signed(&Keypair::new(), vec![], Pubkey::new_unique(), now);
+        assert!(!prune_data.is_expired(now));
+        assert!(!prune_data.is_expired(now + PRUNE_MSG_TIMEOUT_MS));
+        assert!(prune_data.is_expired(now + PRUNE_MSG_TIMEOUT_MS + 1));
+    }
+}
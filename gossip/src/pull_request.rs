@@ -1,14 +1,21 @@
 use {
     crate::{
+        connection::Packet,
         gossip_data::{
             contact_info::ContactInfo,
-            filter::DataFilter,
+            filter::{DataFilter, DataFilterSet, KEYS},
             gossip_data::{GossipData, GossipValue},
         },
         protocol::Protocol,
     },
-    bincode::serialize,
-    solana_sdk::signature::Keypair,
+    bincode::{serialize, serialized_size},
+    solana_bloom::bloom::Bloom,
+    solana_sdk::{
+        hash::Hash,
+        packet::PACKET_DATA_SIZE,
+        signature::{Keypair, Signature},
+    },
+    std::net::SocketAddr,
     thiserror::Error,
 };
 
@@ -16,9 +23,9 @@ pub fn create_pull_request_message(
     contact_info: ContactInfo,
     filter: DataFilter,
     keypair: &Keypair,
-) -> Result<Vec<u8>, PushMessagesErrors> {
+) -> Result<Vec<u8>, PullRequestErrors> {
     if contact_info.sockets().is_empty() {
-        return Err(PushMessagesErrors::NoSocketEntry);
+        return Err(PullRequestErrors::NoSocketEntry);
     }
 
     let signed_data = GossipValue::new_signed(GossipData::ContactInfo(contact_info), keypair);
@@ -27,26 +34,86 @@ pub fn create_pull_request_message(
 
     let message = match serialize(&protocol) {
         Ok(v) => v,
-        Err(_) => return Err(PushMessagesErrors::SerializeFailed),
+        Err(_) => return Err(PullRequestErrors::SerializeFailed),
     };
 
     Ok(message)
 }
 
+// Largest bloom filter that still lets the pull request, including our own
+// contact info, fit in a single packet.
+pub fn max_bloom_filter_bytes(contact_info: &ContactInfo) -> usize {
+    let caller = GossipValue {
+        signature: Signature::default(),
+        data: GossipData::ContactInfo(contact_info.clone()),
+    };
+    let mut empty_filter = DataFilter::default();
+    empty_filter.filter = Bloom::new(0, vec![0; KEYS as usize]);
+    let overhead = serialized_size(&Protocol::PullRequest(empty_filter, caller))
+        .expect("failed to serialize pull request") as usize;
+    // A non empty bitvec adds the slice length on top of its blocks, and the
+    // blocks are 64 bits wide.
+    let max_bytes = PACKET_DATA_SIZE.saturating_sub(overhead + 8);
+    max_bytes - max_bytes % 8
+}
+
+pub fn build_data_filters(contact_info: &ContactInfo, hashes: &[Hash]) -> Vec<DataFilter> {
+    let max_bytes = max_bloom_filter_bytes(contact_info);
+    let mut filters = DataFilterSet::new(hashes.len(), max_bytes);
+    for hash in hashes {
+        filters.add(hash);
+    }
+    filters.into()
+}
+
+// One pull request per filter, handed out to the peers in turn so a single
+// round covers the whole table.
+pub fn create_pull_requests(
+    contact_info: ContactInfo,
+    filters: Vec<DataFilter>,
+    peers: &[SocketAddr],
+    keypair: &Keypair,
+) -> Result<Vec<Packet>, PullRequestErrors> {
+    if contact_info.sockets().is_empty() {
+        return Err(PullRequestErrors::NoSocketEntry);
+    }
+    if peers.is_empty() {
+        return Err(PullRequestErrors::NoPeers);
+    }
+
+    let signed_data = GossipValue::new_signed(GossipData::ContactInfo(contact_info), keypair);
+
+    filters
+        .into_iter()
+        .zip(peers.iter().cycle())
+        .map(|(filter, peer)| {
+            let protocol = Protocol::PullRequest(filter, signed_data.clone());
+            let message = serialize(&protocol).map_err(|_| PullRequestErrors::SerializeFailed)?;
+            if message.len() > PACKET_DATA_SIZE {
+                return Err(PullRequestErrors::PacketTooLarge(message.len()));
+            }
+            Ok((message, *peer))
+        })
+        .collect()
+}
+
 #[derive(Debug, Error)]
-pub enum PushMessagesErrors {
+pub enum PullRequestErrors {
     #[error("No socket adress in contact info")]
     NoSocketEntry,
     #[error("Failed to serialize message")]
     SerializeFailed,
+    #[error("No peers to send pull requests to")]
+    NoPeers,
+    #[error("Pull request of {0} bytes does not fit in a packet")]
+    PacketTooLarge(usize),
 }
 
 #[cfg(test)]
 mod tests {
     use {
         super::*,
-        solana_sdk::{signer::Signer, timing::timestamp},
-        std::net::SocketAddr,
+        solana_sdk::{hash::hashv, signer::Signer, timing::timestamp},
     };
 
     #[test]
@@ -73,4 +140,32 @@ mod tests {
 
         assert!(pull_request.is_ok())
     }
+
+    #[test]
+    fn test_create_pull_requests_fit_in_packet() {
+        let keypair = Keypair::new();
+        let gossip: SocketAddr = "127.0.0.1:8100".parse().unwrap();
+        let contact_info = ContactInfo::new(keypair.pubkey(), timestamp(), 0, gossip);
+        let hashes: Vec<Hash> = (0u64..20_000).map(|i| hashv(&[&i.to_le_bytes()])).collect();
+        let peers: Vec<SocketAddr> = (8001..8004)
+            .map(|port| SocketAddr::from(([127, 0, 0, 1], port)))
+            .collect();
+
+        let filters = build_data_filters(&contact_info, &hashes);
+        assert!(filters.len() > 1);
+        for hash in &hashes {
+            let matches: Vec<&DataFilter> = filters.iter().filter(|f| f.test_mask(hash)).collect();
+            assert_eq!(matches.len(), 1);
+            assert!(matches[0].contains(hash));
+        }
+
+        let num_filters = filters.len();
+        let requests = create_pull_requests(contact_info, filters, &peers, &keypair).unwrap();
+
+        assert_eq!(requests.len(), num_filters);
+        for (i, (message, peer)) in requests.iter().enumerate() {
+            assert_eq!(*peer, peers[i % peers.len()]);
+            assert!(message.len() <= PACKET_DATA_SIZE);
+        }
+    }
 }
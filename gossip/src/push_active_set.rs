@@ -0,0 +1,145 @@
+use {
+    crate::peer_sampler::Peer,
+    solana_sdk::pubkey::Pubkey,
+    std::collections::{HashSet, VecDeque},
+};
+
+pub const PUSH_ACTIVE_SET_SIZE: usize = 12;
+
+struct PushActiveSetEntry {
+    peer: Peer,
+    // Origins this peer asked us to stop pushing.
+    pruned: HashSet<Pubkey>,
+}
+
+// Push peers ordered from oldest to newest. Every rotation brings in one new
+// peer and drops the oldest, so the set slowly turns over.
+pub struct PushActiveSet {
+    size: usize,
+    entries: VecDeque<PushActiveSetEntry>,
+}
+
+impl Default for PushActiveSet {
+    fn default() -> Self {
+        Self::new(PUSH_ACTIVE_SET_SIZE)
+    }
+}
+
+impl PushActiveSet {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            entries: VecDeque::with_capacity(size + 1),
+        }
+    }
+
+    // Peers to push a value from `origin` to, skipping the origin itself and
+    // every peer that pruned it.
+    pub fn get_nodes<'a>(
+        &'a self,
+        origin: &'a Pubkey,
+        fanout: usize,
+    ) -> impl Iterator<Item = &'a Peer> + 'a {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.peer.pubkey != *origin && !entry.pruned.contains(origin))
+            .map(|entry| &entry.peer)
+            .take(fanout)
+    }
+
+    pub fn prune(&mut self, peer: &Pubkey, origins: &[Pubkey]) {
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.peer.pubkey == *peer)
+        {
+            entry
+                .pruned
+                .extend(origins.iter().filter(|origin| *origin != peer));
+        }
+    }
+
+    // `candidates` is expected in stake-weighted random order.
+    pub fn rotate(&mut self, candidates: Vec<Peer>) {
+        for peer in candidates {
+            if self.entries.len() > self.size {
+                break;
+            }
+            if self.contains(&peer.pubkey) {
+                continue;
+            }
+            self.entries.push_back(PushActiveSetEntry {
+                peer,
+                pruned: HashSet::new(),
+            });
+        }
+        while self.entries.len() > self.size {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn contains(&self, peer: &Pubkey) -> bool {
+        self.entries.iter().any(|entry| entry.peer.pubkey == *peer)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::net::SocketAddr};
+
+    fn new_peers(num: u16) -> Vec<Peer> {
+        (0..num)
+            .map(|i| Peer {
+                pubkey: Pubkey::new_unique(),
+                gossip: SocketAddr::from(([127, 0, 0, 1], 8000 + i)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rotate_drops_oldest() {
+        let peers = new_peers(5);
+        let mut active_set = PushActiveSet::new(3);
+
+        active_set.rotate(peers[..4].to_vec());
+        assert_eq!(active_set.len(), 3);
+        assert!(!active_set.contains(&peers[0].pubkey));
+
+        active_set.rotate(vec![peers[2], peers[4]]);
+        assert_eq!(active_set.len(), 3);
+        assert!(!active_set.contains(&peers[1].pubkey));
+        assert!(active_set.contains(&peers[4].pubkey));
+    }
+
+    #[test]
+    fn test_prune_excludes_origin() {
+        let peers = new_peers(3);
+        let origin = Pubkey::new_unique();
+        let mut active_set = PushActiveSet::new(3);
+        active_set.rotate(peers.clone());
+
+        active_set.prune(&peers[0].pubkey, &[origin, peers[0].pubkey]);
+
+        let nodes: Vec<Peer> = active_set.get_nodes(&origin, 10).copied().collect();
+        assert_eq!(nodes, peers[1..].to_vec());
+        let nodes: Vec<Peer> = active_set
+            .get_nodes(&peers[0].pubkey, 10)
+            .copied()
+            .collect();
+        assert_eq!(nodes, peers[1..].to_vec());
+        let nodes: Vec<Peer> = active_set
+            .get_nodes(&peers[1].pubkey, 10)
+            .copied()
+            .collect();
+        assert_eq!(nodes, vec![peers[0], peers[2]]);
+        assert_eq!(active_set.get_nodes(&Pubkey::new_unique(), 2).count(), 2);
+    }
+}
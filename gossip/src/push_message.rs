@@ -0,0 +1,199 @@
+use {
+    crate::{
+        gossip_data::gossip_data::{GossipLabel, GossipValue},
+        protocol::Protocol,
+    },
+    bincode::{serialize, serialized_size},
+    solana_sdk::{packet::PACKET_DATA_SIZE, pubkey::Pubkey},
+    std::collections::{hash_map::Entry, HashMap},
+    thiserror::Error,
+};
+
+pub const PUSH_FANOUT: usize = 6;
+const PUSH_QUEUE_CAPACITY: usize = 65_536;
+
+// Values waiting to be relayed in the next push round, one per label. A newer
+// revision replaces the queued one, new labels are dropped once full.
+pub struct PushQueue {
+    values: HashMap<GossipLabel, GossipValue>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl Default for PushQueue {
+    fn default() -> Self {
+        Self::new(PUSH_QUEUE_CAPACITY)
+    }
+}
+
+impl PushQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            values: HashMap::new(),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    pub fn extend(&mut self, values: impl IntoIterator<Item = GossipValue>) {
+        for value in values {
+            let full = self.values.len() >= self.capacity;
+            match self.values.entry(value.label()) {
+                Entry::Occupied(mut queued) => {
+                    if value.wallclock() >= queued.get().wallclock() {
+                        queued.insert(value);
+                    }
+                }
+                Entry::Vacant(_) if full => self.dropped += 1,
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
+
+    pub fn drain(&mut self) -> Vec<GossipValue> {
+        self.values.drain().map(|(_, value)| value).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // Values not queued because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+// Packs the values into as few push messages as possible, each one fitting in
+// a single packet.
+pub fn create_push_messages(
+    from: Pubkey,
+    values: Vec<GossipValue>,
+) -> Result<Vec<Vec<u8>>, PushErrors> {
+    pack_values(from, values, Protocol::PushMessage)
+}
+
+pub fn create_pull_responses(
+    from: Pubkey,
+    values: Vec<GossipValue>,
+) -> Result<Vec<Vec<u8>>, PushErrors> {
+    pack_values(from, values, Protocol::PullResponse)
+}
+
+fn pack_values(
+    from: Pubkey,
+    values: Vec<GossipValue>,
+    new_message: fn(Pubkey, Vec<GossipValue>) -> Protocol,
+) -> Result<Vec<Vec<u8>>, PushErrors> {
+    let overhead = serialized_size(&new_message(from, vec![]))
+        .map_err(|_| PushErrors::SerializeFailed)? as usize;
+
+    let mut chunks: Vec<Vec<GossipValue>> = vec![];
+    let mut chunk_size = overhead;
+    for value in values {
+        let size = serialized_size(&value).map_err(|_| PushErrors::SerializeFailed)? as usize;
+        if overhead + size > PACKET_DATA_SIZE {
+            return Err(PushErrors::ValueTooLarge(size));
+        }
+        match chunks.last_mut() {
+            Some(chunk) if chunk_size + size <= PACKET_DATA_SIZE => {
+                chunk.push(value);
+                chunk_size += size;
+            }
+            _ => {
+                chunks.push(vec![value]);
+                chunk_size = overhead + size;
+            }
+        }
+    }
+
+    chunks
+        .into_iter()
+        .map(|chunk| serialize(&new_message(from, chunk)).map_err(|_| PushErrors::SerializeFailed))
+        .collect()
+}
+
+#[derive(Debug, Error)]
+pub enum PushErrors {
+    #[error("Failed to serialize push message")]
+    SerializeFailed,
+    #[error("Value of {0} bytes does not fit in a push message")]
+    ValueTooLarge(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::gossip_data::{
+            contact_info::ContactInfo,
+            gossip_data::{GossipData, NodeInstance},
+        },
+        bincode::deserialize,
+        solana_sdk::{signature::Keypair, signer::Signer, timing::timestamp},
+        std::net::SocketAddr,
+    };
+
+    #[test]
+    fn test_create_push_messages_fit_in_packet() {
+        let keypair = Keypair::new();
+        let gossip: SocketAddr = "127.0.0.1:8001".parse().unwrap();
+        let now = timestamp();
+        let mut values = vec![];
+        for _ in 0..20 {
+            let contact_info = ContactInfo::new(keypair.pubkey(), now, 0, gossip);
+            values.push(GossipValue::new_signed(
+                GossipData::ContactInfo(contact_info),
+                &keypair,
+            ));
+            let instance = NodeInstance::new(&mut rand::thread_rng(), keypair.pubkey(), now);
+            values.push(GossipValue::new_signed(
+                GossipData::NodeInstance(instance),
+                &keypair,
+            ));
+        }
+
+        let messages = create_push_messages(keypair.pubkey(), values).unwrap();
+
+        assert!(messages.len() > 1);
+        let mut num_values = 0;
+        for message in messages {
+            assert!(message.len() <= PACKET_DATA_SIZE);
+            match deserialize(&message).unwrap() {
+                Protocol::PushMessage(from, values) => {
+                    assert_eq!(from, keypair.pubkey());
+                    num_values += values.len();
+                }
+                _ => panic!("expected a push message"),
+            }
+        }
+        assert_eq!(num_values, 40);
+    }
+
+    #[test]
+    fn test_push_queue() {
+        let keypair = Keypair::new();
+        let instance = |wallclock| {
+            let instance = NodeInstance::new(&mut rand::thread_rng(), keypair.pubkey(), wallclock);
+            GossipValue::new_signed(GossipData::NodeInstance(instance), &keypair)
+        };
+        let gossip: SocketAddr = "127.0.0.1:8001".parse().unwrap();
+        let contact_info = ContactInfo::new(keypair.pubkey(), 1, 0, gossip);
+        let contact_info = GossipValue::new_signed(GossipData::ContactInfo(contact_info), &keypair);
+
+        let mut queue = PushQueue::new(1);
+        queue.extend([instance(2), instance(1), contact_info]);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.dropped(), 1);
+        let values = queue.drain();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].wallclock(), 2);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+use {
+    crate::stakes::StakeSnapshot,
+    lru::LruCache,
+    solana_sdk::{hash::Hash, pubkey::Pubkey},
+    std::{cmp::Reverse, collections::HashMap, num::NonZero},
+};
+
+const RECEIVED_CACHE_CAPACITY: usize = 50_000;
+// Only the first senders of a value get credit for it, later ones are
+// redundant and become prune candidates.
+pub const RECEIVED_CACHE_NUM_SENDERS: usize = 3;
+// Wait for enough fresh values from an origin before judging its senders.
+const MIN_NUM_UPSERTS: usize = 20;
+// Same thresholds the validator uses when pruning push senders.
+pub const PRUNE_STAKE_THRESHOLD_PCT: f64 = 0.15;
+pub const PRUNE_MIN_INGRESS_NODES: usize = 2;
+
+#[derive(Default)]
+struct ReceivedCacheEntry {
+    // Number of times each sender was among the first to deliver a value.
+    senders: HashMap<Pubkey, usize>,
+    num_upserts: usize,
+}
+
+pub struct ReceivedCache {
+    // Number of deliveries seen so far per value hash.
+    deliveries: LruCache<Hash, usize>,
+    origins: LruCache<Pubkey, ReceivedCacheEntry>,
+}
+
+impl Default for ReceivedCache {
+    fn default() -> Self {
+        Self::new(NonZero::new(RECEIVED_CACHE_CAPACITY).unwrap())
+    }
+}
+
+impl ReceivedCache {
+    pub fn new(cap: NonZero<usize>) -> Self {
+        Self {
+            deliveries: LruCache::new(cap),
+            origins: LruCache::new(cap),
+        }
+    }
+
+    pub fn record(&mut self, origin: Pubkey, hash: Hash, sender: Pubkey) {
+        let deliveries = self.deliveries.get_or_insert_mut(hash, || 0);
+        let num_dups = *deliveries;
+        *deliveries += 1;
+
+        let entry = self
+            .origins
+            .get_or_insert_mut(origin, ReceivedCacheEntry::default);
+        if num_dups == 0 {
+            entry.num_upserts += 1;
+        }
+        let score = entry.senders.entry(sender).or_default();
+        if num_dups < RECEIVED_CACHE_NUM_SENDERS {
+            *score += 1;
+        }
+    }
+
+    // Senders that keep pushing `origin` without being among the first to do
+    // so. The best senders are kept until they cover enough ingress nodes and
+    // stake, everything after them is pruned. Resets the origin's scores.
+    pub fn prune(
+        &mut self,
+        pubkey: &Pubkey,
+        origin: &Pubkey,
+        stakes: &StakeSnapshot,
+    ) -> Vec<Pubkey> {
+        let Some(entry) = self.origins.get_mut(origin) else {
+            return vec![];
+        };
+        if entry.num_upserts < MIN_NUM_UPSERTS {
+            return vec![];
+        }
+        let entry = std::mem::take(entry);
+
+        let mut senders: Vec<(Pubkey, usize)> = entry.senders.into_iter().collect();
+        senders.sort_unstable_by_key(|(sender, score)| Reverse((*score, stakes.get(sender))));
+
+        let min_ingress_stake = stakes.get(pubkey).min(stakes.get(origin)) as f64;
+        let min_ingress_stake = (min_ingress_stake * PRUNE_STAKE_THRESHOLD_PCT) as u64;
+        let mut ingress_stake = 0u64;
+        senders
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, (sender, _))| {
+                if index < PRUNE_MIN_INGRESS_NODES || ingress_stake < min_ingress_stake {
+                    ingress_stake = ingress_stake.saturating_add(stakes.get(&sender));
+                    None
+                } else {
+                    Some(sender)
+                }
+            })
+            .filter(|sender| sender != origin)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::hash::hash};
+
+    #[test]
+    fn test_prune_redundant_senders() {
+        let pubkey = Pubkey::new_unique();
+        let origin = Pubkey::new_unique();
+        let senders: Vec<Pubkey> = std::iter::repeat_with(Pubkey::new_unique).take(5).collect();
+        let stakes = StakeSnapshot::new(HashMap::from([
+            (pubkey, 1_000),
+            (origin, 1_000),
+            (senders[0], 10),
+            (senders[1], 10),
+            (senders[2], 200),
+            (senders[3], 500),
+        ]));
+        let mut cache = ReceivedCache::default();
+
+        for i in 0..MIN_NUM_UPSERTS - 1 {
+            let hash = hash(&i.to_le_bytes());
+            for sender in &senders {
+                cache.record(origin, hash, *sender);
+            }
+        }
+        assert!(cache.prune(&pubkey, &origin, &stakes).is_empty());
+
+        // senders[4] is never among the first three, senders[3] only once.
+        let hash = hash(b"last");
+        for sender in [senders[3], senders[0], senders[1], senders[2], senders[4]] {
+            cache.record(origin, hash, sender);
+        }
+        // Ingress needs 150 stake: the two best senders bring 20 and senders[2]
+        // tops it up, so senders[3] and senders[4] get pruned.
+        let mut prunes = cache.prune(&pubkey, &origin, &stakes);
+        prunes.sort();
+        let mut expected = vec![senders[3], senders[4]];
+        expected.sort();
+        assert_eq!(prunes, expected);
+        assert!(cache.prune(&pubkey, &origin, &stakes).is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use {
+    serde_json::Value,
+    solana_sdk::pubkey::Pubkey,
+    std::{collections::HashMap, fs, path::Path, str::FromStr},
+    thiserror::Error,
+};
+
+const PUBKEY_KEYS: [&str; 4] = ["identityPubkey", "nodePubkey", "identity", "pubkey"];
+const STAKE_KEYS: [&str; 2] = ["activatedStake", "stake"];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StakeSnapshot {
+    stakes: HashMap<Pubkey, u64>,
+}
+
+impl StakeSnapshot {
+    pub fn new(stakes: HashMap<Pubkey, u64>) -> Self {
+        Self { stakes }
+    }
+
+    // Accepts `solana validators --output json`, a `getVoteAccounts` response
+    // or a CSV with identity and stake columns.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, StakeErrors> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).map_err(|e| StakeErrors::ReadFailed(e.to_string()))?;
+        let is_csv = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            Self::from_csv(&data)
+        } else {
+            Self::from_json(&data)
+        }
+    }
+
+    pub fn from_json(data: &str) -> Result<Self, StakeErrors> {
+        let value: Value =
+            serde_json::from_str(data).map_err(|e| StakeErrors::InvalidJson(e.to_string()))?;
+        let value = value.get("result").unwrap_or(&value);
+
+        let accounts: Vec<&Value> = match value {
+            Value::Array(accounts) => accounts.iter().collect(),
+            Value::Object(_) => ["validators", "current", "delinquent"]
+                .iter()
+                .filter_map(|key| value.get(key)?.as_array())
+                .flatten()
+                .collect(),
+            _ => {
+                return Err(StakeErrors::InvalidJson(
+                    "expected an object or array".into(),
+                ))
+            }
+        };
+
+        let mut stakes = HashMap::new();
+        for account in accounts {
+            let pubkey = PUBKEY_KEYS
+                .iter()
+                .find_map(|key| account.get(key)?.as_str())
+                .ok_or(StakeErrors::MissingPubkey)?;
+            let stake = STAKE_KEYS
+                .iter()
+                .find_map(|key| account.get(key)?.as_u64())
+                .ok_or(StakeErrors::MissingStake)?;
+            let pubkey = parse_pubkey(pubkey)?;
+            *stakes.entry(pubkey).or_default() += stake;
+        }
+        Ok(Self::new(stakes))
+    }
+
+    pub fn from_csv(data: &str) -> Result<Self, StakeErrors> {
+        let mut lines = data.lines().map(str::trim).filter(|line| !line.is_empty());
+        let mut columns = (0, 1);
+        let mut stakes = HashMap::new();
+
+        if let Some(first) = lines.next() {
+            let fields: Vec<&str> = first.split(',').map(str::trim).collect();
+            let find = |keys: &[&str]| {
+                fields
+                    .iter()
+                    .position(|field| keys.iter().any(|key| field.eq_ignore_ascii_case(key)))
+            };
+            match (find(&PUBKEY_KEYS), find(&STAKE_KEYS)) {
+                (Some(pubkey), Some(stake)) => columns = (pubkey, stake),
+                _ => parse_csv_line(&fields, columns, &mut stakes)?,
+            }
+        }
+        for line in lines {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            parse_csv_line(&fields, columns, &mut stakes)?;
+        }
+        Ok(Self::new(stakes))
+    }
+
+    pub fn get(&self, pubkey: &Pubkey) -> u64 {
+        self.stakes.get(pubkey).copied().unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stakes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stakes.is_empty()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.stakes.values().sum()
+    }
+}
+
+fn parse_csv_line(
+    fields: &[&str],
+    (pubkey, stake): (usize, usize),
+    stakes: &mut HashMap<Pubkey, u64>,
+) -> Result<(), StakeErrors> {
+    let pubkey = fields.get(pubkey).ok_or(StakeErrors::MissingPubkey)?;
+    let stake = fields.get(stake).ok_or(StakeErrors::MissingStake)?;
+    let stake = stake
+        .parse::<u64>()
+        .map_err(|_| StakeErrors::InvalidStake(stake.to_string()))?;
+    *stakes.entry(parse_pubkey(pubkey)?).or_default() += stake;
+    Ok(())
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<Pubkey, StakeErrors> {
+    Pubkey::from_str(pubkey).map_err(|_| StakeErrors::InvalidPubkey(pubkey.to_string()))
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StakeErrors {
+    #[error("Failed to read stakes file: {0}")]
+    ReadFailed(String),
+    #[error("Invalid stakes json: {0}")]
+    InvalidJson(String),
+    #[error("Stake entry has no pubkey")]
+    MissingPubkey,
+    #[error("Stake entry has no stake")]
+    MissingStake,
+    #[error("Invalid pubkey {0}")]
+    InvalidPubkey(String),
+    #[error("Invalid stake {0}")]
+    InvalidStake(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stakes_from_json() {
+        let node = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let validators = format!(
+            r#"{{"validators":[{{"identityPubkey":"{node}","activatedStake":100}},
+            {{"identityPubkey":"{other}","activatedStake":7}}]}}"#
+        );
+        let stakes = StakeSnapshot::from_json(&validators).unwrap();
+        assert_eq!(stakes.get(&node), 100);
+        assert_eq!(stakes.get(&other), 7);
+
+        let vote_accounts = format!(
+            r#"{{"jsonrpc":"2.0","result":{{
+            "current":[{{"nodePubkey":"{node}","activatedStake":100}}],
+            "delinquent":[{{"nodePubkey":"{other}","activatedStake":7}}]}}}}"#
+        );
+        assert_eq!(StakeSnapshot::from_json(&vote_accounts).unwrap(), stakes);
+
+        assert_eq!(
+            StakeSnapshot::from_json(r#"[{"activatedStake":1}]"#),
+            Err(StakeErrors::MissingPubkey)
+        );
+    }
+
+    #[test]
+    fn test_stakes_from_csv() {
+        let node = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let with_header = format!("votePubkey,stake,identity\nx,100,{node}\ny,7,{other}\n");
+        let stakes = StakeSnapshot::from_csv(&with_header).unwrap();
+        assert_eq!(stakes.get(&node), 100);
+        assert_eq!(stakes.get(&other), 7);
+        assert_eq!(stakes.total(), 107);
+
+        let without_header = format!("{node},100\n{other},7");
+        assert_eq!(StakeSnapshot::from_csv(&without_header).unwrap(), stakes);
+
+        assert_eq!(
+            StakeSnapshot::from_csv(&format!("{node},lots")),
+            Err(StakeErrors::InvalidStake("lots".to_string()))
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use {
+    crate::{
+        gossip_data::gossip_data::{CompressedSlots, GossipData, GossipValue},
+        gossip_table::{GossipEntry, GossipTable},
+        handler::GossipHandler,
+        json::data_json,
+    },
+    bincode::{deserialize, serialize},
+    rusqlite::{params, Connection},
+    solana_sdk::clock::Slot,
+    std::{
+        collections::HashSet,
+        path::Path,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            mpsc::{self, Receiver, SyncSender, TrySendError},
+            Mutex,
+        },
+        thread::{self, JoinHandle},
+    },
+    thiserror::Error,
+};
+
+// One table per data type, in the order of the `GossipData` variants.
+pub const TABLES: [&str; 14] = [
+    "legacy_contact_info",
+    "vote",
+    "lowest_slot",
+    "legacy_snapshot_hashes",
+    "accounts_hashes",
+    "epoch_slots",
+    "legacy_version",
+    "version",
+    "node_instance",
+    "duplicate_shred",
+    "snapshot_hashes",
+    "contact_info",
+    "restart_last_voted_fork_slots",
+    "restart_heaviest_fork",
+];
+
+const WRITE_QUEUE_CAPACITY: usize = 65_536;
+const MAX_WRITE_BATCH: usize = 4_096;
+// Warns on the first dropped revision and every this many after it.
+const DROPPED_WARN_INTERVAL: u64 = 10_000;
+
+pub fn table_name(data: &GossipData) -> &'static str {
+    match data {
+        GossipData::LegacyContactInfo(_) => TABLES[0],
+        GossipData::Vote(_, _) => TABLES[1],
+        GossipData::LowestSlot(_, _) => TABLES[2],
+        GossipData::LegacySnapshotHashes(_) => TABLES[3],
+        GossipData::AccountsHashes(_) => TABLES[4],
+        GossipData::EpochSlots(_, _) => TABLES[5],
+        GossipData::LegacyVersion(_) => TABLES[6],
+        GossipData::Version(_) => TABLES[7],
+        GossipData::NodeInstance(_) => TABLES[8],
+        GossipData::DuplicateShred(_, _) => TABLES[9],
+        GossipData::SnapshotHashes(_) => TABLES[10],
+        GossipData::ContactInfo(_) => TABLES[11],
+        GossipData::RestartLastVotedForkSlots(_) => TABLES[12],
+        GossipData::RestartHeaviestFork(_) => TABLES[13],
+    }
+}
+
+pub fn parse_table(name: &str) -> Result<&'static str, StoreErrors> {
+    TABLES
+        .into_iter()
+        .find(|table| *table == name)
+        .ok_or_else(|| StoreErrors::UnknownTable(name.to_string()))
+}
+
+// The slot a value is about, for the types that have one.
+fn value_slot(data: &GossipData) -> Option<Slot> {
+    match data {
+        GossipData::Vote(_, vote) => vote.slots().into_iter().max(),
+        GossipData::LowestSlot(_, slots) => Some(slots.lowest),
+        GossipData::LegacySnapshotHashes(hashes) | GossipData::AccountsHashes(hashes) => {
+            hashes.hashes.iter().map(|(slot, _)| *slot).max()
+        }
+        GossipData::EpochSlots(_, slots) => slots
+            .slots
+            .iter()
+            .map(|slots| match slots {
+                CompressedSlots::Flate2(slots) => slots.first_slot,
+                CompressedSlots::Uncompressed(slots) => slots.first_slot,
+            })
+            .min(),
+        GossipData::DuplicateShred(_, shred) => Some(shred.slot),
+        GossipData::SnapshotHashes(hashes) => Some(hashes.full.0),
+        GossipData::RestartLastVotedForkSlots(slots) => Some(slots.last_voted_slot),
+        GossipData::RestartHeaviestFork(fork) => Some(fork.last_slot),
+        GossipData::LegacyContactInfo(_)
+        | GossipData::LegacyVersion(_)
+        | GossipData::Version(_)
+        | GossipData::NodeInstance(_)
+        | GossipData::ContactInfo(_) => None,
+    }
+}
+
+// SQLite integers are signed, anything past i64::MAX is clamped.
+fn to_sql(n: u64) -> i64 {
+    i64::try_from(n).unwrap_or(i64::MAX)
+}
+
+// Every revision of the values accepted into the table. `data` holds the
+// same JSON as the HTTP API, so fields can be queried with `json_extract`.
+pub struct GossipStore {
+    connection: Connection,
+}
+
+impl GossipStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreErrors> {
+        Self::new(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreErrors> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> Result<Self, StoreErrors> {
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        for table in TABLES {
+            connection.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {table} (
+                    hash TEXT PRIMARY KEY,
+                    pubkey TEXT NOT NULL,
+                    label TEXT NOT NULL,
+                    slot INTEGER,
+                    wallclock INTEGER NOT NULL,
+                    local_timestamp INTEGER NOT NULL,
+                    data TEXT NOT NULL,
+                    value BLOB NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS {table}_pubkey ON {table} (pubkey, wallclock);
+                CREATE INDEX IF NOT EXISTS {table}_slot ON {table} (slot);
+                CREATE INDEX IF NOT EXISTS {table}_wallclock ON {table} (wallclock);"
+            ))?;
+        }
+        Ok(Self { connection })
+    }
+
+    // Writes the entries in one transaction. Revisions already stored are
+    // skipped, returns how many were new.
+    pub fn insert(&mut self, entries: &[GossipEntry]) -> Result<usize, StoreErrors> {
+        let transaction = self.connection.transaction()?;
+        let mut inserted = 0;
+        for entry in entries {
+            let value = &entry.value;
+            let bytes = serialize(value).map_err(|_| StoreErrors::SerializeFailed)?;
+            let mut statement = transaction.prepare_cached(&format!(
+                "INSERT OR IGNORE INTO {} \
+                 (hash, pubkey, label, slot, wallclock, local_timestamp, data, value) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                table_name(&value.data)
+            ))?;
+            inserted += statement.execute(params![
+                entry.hash.to_string(),
+                value.pubkey().to_string(),
+                format!("{:?}", value.label()),
+                value_slot(&value.data).map(to_sql),
+                to_sql(value.wallclock()),
+                to_sql(entry.local_timestamp),
+                data_json(&value.data).to_string(),
+                bytes,
+            ])?;
+        }
+        transaction.commit()?;
+        Ok(inserted)
+    }
+
+    // The newest stored revision of every label.
+    pub fn latest(&self) -> Result<Vec<GossipValue>, StoreErrors> {
+        let mut values = vec![];
+        for table in TABLES {
+            // SQLite takes the bare `value` column from the row holding the max.
+            let mut statement = self.connection.prepare(&format!(
+                "SELECT value, MAX(wallclock) FROM {} GROUP BY label",
+                table
+            ))?;
+            let rows = statement.query_map([], |row| row.get::<_, Vec<u8>>(0))?;
+            for bytes in rows {
+                match deserialize(&bytes?) {
+                    Ok(value) => values.push(value),
+                    Err(err) => log::warn!("Skipping unreadable {} value: {}", table, err),
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    // Fills the table with the latest stored values, as if received at
+    // `now`. Values nobody refreshes are purged like any other.
+    pub fn warm_start(&self, table: &mut GossipTable, now: u64) -> Result<usize, StoreErrors> {
+        let loaded = self
+            .latest()?
+            .into_iter()
+            .map(|value| table.insert(value, now))
+            .filter(Result::is_ok)
+            .count();
+        Ok(loaded)
+    }
+
+    // Moves the store to a writer thread fed by the returned handler. Only
+    // the given tables are written, all of them if `None`. Call `close` on
+    // the writer before exiting, or queued values are lost.
+    pub fn spawn_writer(self, tables: Option<HashSet<&'static str>>) -> StoreWriter {
+        let (sender, receiver) = mpsc::sync_channel(WRITE_QUEUE_CAPACITY);
+        let thread = thread::Builder::new()
+            .name("gossip-store".to_string())
+            .spawn(move || self.write_loop(receiver))
+            .expect("failed to spawn store writer");
+        StoreWriter {
+            sender: Mutex::new(Some(sender)),
+            thread: Mutex::new(Some(thread)),
+            tables,
+            dropped: AtomicU64::default(),
+        }
+    }
+
+    fn write_loop(mut self, receiver: Receiver<GossipEntry>) {
+        while let Ok(entry) = receiver.recv() {
+            let mut batch = vec![entry];
+            batch.extend(receiver.try_iter().take(MAX_WRITE_BATCH - 1));
+            if let Err(err) = self.insert(&batch) {
+                log::warn!("Failed to store {} values: {}", batch.len(), err);
+            }
+        }
+    }
+}
+
+pub struct StoreWriter {
+    // Taken on close, values accepted after that are not stored.
+    sender: Mutex<Option<SyncSender<GossipEntry>>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    tables: Option<HashSet<&'static str>>,
+    dropped: AtomicU64,
+}
+
+impl StoreWriter {
+    // Stops storing and waits until every queued value is written.
+    pub fn close(&self) {
+        drop(self.sender.lock().unwrap().take());
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            log::info!("Writing queued values to the store");
+            if thread.join().is_err() {
+                log::warn!("Store writer panicked");
+            }
+        }
+    }
+
+    // Revisions not stored because the writer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl GossipHandler for StoreWriter {
+    fn on_accepted(&self, entry: &GossipEntry) {
+        if let Some(tables) = &self.tables {
+            if !tables.contains(table_name(&entry.value.data)) {
+                return;
+            }
+        }
+        let sender = self.sender.lock().unwrap();
+        let Some(sender) = sender.as_ref() else {
+            return;
+        };
+        match sender.try_send(entry.clone()) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed);
+                if dropped.is_multiple_of(DROPPED_WARN_INTERVAL) {
+                    log::warn!(
+                        "Store writer is behind, {} values dropped so far",
+                        dropped + 1
+                    );
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                log::warn!("Store writer stopped, dropping value");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StoreErrors {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Failed to serialize value")]
+    SerializeFailed,
+    #[error("Unknown table {0}, expected one of {}", TABLES.join(", "))]
+    UnknownTable(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::gossip_data::{contact_info::ContactInfo, gossip_data::GossipLabel},
+        solana_sdk::{signature::Keypair, signer::Signer},
+        std::net::SocketAddr,
+    };
+
+    fn contact_info(keypair: &Keypair, wallclock: u64, gossip: SocketAddr) -> GossipValue {
+        let contact_info = ContactInfo::new(keypair.pubkey(), wallclock, 0, gossip);
+        GossipValue::new_signed(GossipData::ContactInfo(contact_info), keypair)
+    }
+
+    #[test]
+    fn test_store_history() {
+        let keypair = Keypair::new();
+        let mut table = GossipTable::default();
+        let mut store = GossipStore::open_in_memory().unwrap();
+        for (wallclock, gossip) in [(1_000, "10.0.0.1:8001"), (2_000, "10.0.0.2:8001")] {
+            let value = contact_info(&keypair, wallclock, gossip.parse().unwrap());
+            table.insert(value.clone(), wallclock).unwrap();
+            let entry = table.get(&value.label()).unwrap().clone();
+            assert_eq!(store.insert(std::slice::from_ref(&entry)).unwrap(), 1);
+            assert_eq!(store.insert(&[entry]).unwrap(), 0);
+        }
+
+        let history: Vec<(i64, String)> = store
+            .connection
+            .prepare(
+                "SELECT wallclock, json_extract(data, '$.sockets.gossip') FROM contact_info \
+                 WHERE pubkey = ?1 ORDER BY wallclock",
+            )
+            .unwrap()
+            .query_map([keypair.pubkey().to_string()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            history,
+            vec![
+                (1_000, "10.0.0.1:8001".to_string()),
+                (2_000, "10.0.0.2:8001".to_string()),
+            ]
+        );
+
+        let mut warm = GossipTable::default();
+        assert_eq!(store.warm_start(&mut warm, 3_000).unwrap(), 1);
+        let entry = warm
+            .get(&GossipLabel::ContactInfo(keypair.pubkey()))
+            .unwrap();
+        assert_eq!(entry.value.wallclock(), 2_000);
+        assert_eq!(entry.local_timestamp, 3_000);
+        assert!(parse_table("vote").is_ok());
+        assert!(parse_table("Vote").is_err());
+    }
+
+    #[test]
+    fn test_writer_flushes_on_close() {
+        let path = std::env::temp_dir().join(format!("store-{}.db", std::process::id()));
+        let writer = GossipStore::open(&path).unwrap().spawn_writer(None);
+        let keypair = Keypair::new();
+        let gossip = "10.0.0.1:8001".parse().unwrap();
+        let entries: Vec<GossipEntry> = (1..=100)
+            .map(|wallclock| {
+                let value = contact_info(&keypair, wallclock, gossip);
+                let mut table = GossipTable::default();
+                table.insert(value.clone(), wallclock).unwrap();
+                table.get(&value.label()).unwrap().clone()
+            })
+            .collect();
+        for entry in &entries {
+            writer.on_accepted(entry);
+        }
+        writer.close();
+        // Ignored once closed.
+        writer.on_accepted(&entries[0]);
+
+        let stored: i64 = GossipStore::open(&path)
+            .unwrap()
+            .connection
+            .query_row("SELECT COUNT(*) FROM contact_info", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, 100);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
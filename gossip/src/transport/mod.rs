@@ -0,0 +1,29 @@
+use {
+    crate::connection::Packet,
+    std::{future::Future, io, net::SocketAddr, pin::Pin},
+};
+
+pub mod replay;
+pub mod sim;
+pub mod udp;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+// Moves datagrams for a `Connection`, either over a real socket or through an
+// in-memory network.
+pub trait Transport: Send + Sync {
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+
+    // Waits for at least one datagram and fills up to `bufs.len()` of them.
+    // Returns the received size and source of each, in the order of `bufs`.
+    fn recv_batch<'a>(
+        &'a self,
+        bufs: &'a mut [Vec<u8>],
+    ) -> BoxFuture<'a, io::Result<Vec<(usize, SocketAddr)>>>;
+
+    // Returns the packets that could not be sent.
+    fn send_batch<'a>(
+        &'a self,
+        packets: &'a [Packet],
+    ) -> BoxFuture<'a, Vec<(SocketAddr, io::Error)>>;
+}
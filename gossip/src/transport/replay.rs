@@ -0,0 +1,203 @@
+use {
+    super::{BoxFuture, Transport},
+    crate::{
+        capture::{CaptureReader, CaptureRecord, Direction},
+        connection::Packet,
+    },
+    std::{
+        collections::VecDeque, fs::File, io, io::BufReader, io::Read, net::SocketAddr, path::Path,
+        time::Duration,
+    },
+    tokio::{
+        sync::{watch, Mutex},
+        task,
+        time::{sleep_until, Instant},
+    },
+};
+
+// Inbound records read from the file at a time.
+const READ_AHEAD: usize = 256;
+
+// Reads up to `READ_AHEAD` inbound records, outbound ones are skipped. The
+// reader is returned unless the capture ended.
+fn read_ahead<R: Read>(
+    mut reader: CaptureReader<R>,
+) -> (Option<CaptureReader<R>>, VecDeque<CaptureRecord>) {
+    let mut records = VecDeque::new();
+    while records.len() < READ_AHEAD {
+        match reader.next_record() {
+            Ok(Some(record)) if record.direction == Direction::Inbound => records.push_back(record),
+            Ok(Some(_)) => continue,
+            Ok(None) => return (None, records),
+            Err(e) => {
+                log::warn!("Stopping replay at a broken record {:?}", e);
+                return (None, records);
+            }
+        }
+    }
+    (Some(reader), records)
+}
+
+struct ReplayState<R> {
+    // `None` once the capture is read to the end.
+    reader: Option<CaptureReader<R>>,
+    records: VecDeque<CaptureRecord>,
+    // Capture time of the first record and when it was replayed.
+    start: Option<(u64, Instant)>,
+}
+
+impl<R: Read + Send + 'static> ReplayState<R> {
+    // Timestamp of the next inbound record. Reads happen on the blocking pool.
+    async fn peek(&mut self) -> Option<u64> {
+        if self.records.is_empty() {
+            let reader = self.reader.take()?;
+            match task::spawn_blocking(move || read_ahead(reader)).await {
+                Ok((reader, records)) => {
+                    self.reader = reader;
+                    self.records = records;
+                }
+                Err(e) => log::warn!("Replay reader failed {:?}", e),
+            }
+        }
+        self.records.front().map(|record| record.timestamp_us)
+    }
+
+    // When a record is due, keeping the gaps of the capture scaled by `speed`.
+    fn due(&mut self, timestamp_us: u64, speed: f64) -> Instant {
+        let (first_us, started) = *self.start.get_or_insert((timestamp_us, Instant::now()));
+        let elapsed = timestamp_us.saturating_sub(first_us) as f64 / 1e6;
+        started + Duration::from_secs_f64(elapsed / speed)
+    }
+}
+
+// Feeds the inbound datagrams of a capture to a `Connection` as if they just
+// arrived, in the recorded order. Sent packets go nowhere.
+pub struct ReplayTransport<R> {
+    local_addr: SocketAddr,
+    // 1.0 keeps the recorded timing, 10.0 replays ten times faster. `None`
+    // replays as fast as the node takes packets.
+    speed: Option<f64>,
+    state: Mutex<ReplayState<R>>,
+    finished: watch::Sender<bool>,
+}
+
+impl ReplayTransport<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>, speed: Option<f64>) -> io::Result<Self> {
+        Ok(Self::new(CaptureReader::open(path)?, speed))
+    }
+}
+
+impl<R: Read + Send + 'static> ReplayTransport<R> {
+    pub fn new(reader: CaptureReader<R>, speed: Option<f64>) -> Self {
+        Self {
+            local_addr: reader.local_addr(),
+            speed: speed.filter(|speed| *speed > 0.0),
+            state: Mutex::new(ReplayState {
+                reader: Some(reader),
+                records: VecDeque::new(),
+                start: None,
+            }),
+            finished: watch::channel(false).0,
+        }
+    }
+
+    // Resolves once every inbound record was handed to the connection.
+    pub async fn finished(&self) {
+        let _ = self
+            .finished
+            .subscribe()
+            .wait_for(|finished| *finished)
+            .await;
+    }
+
+    async fn recv(&self, bufs: &mut [Vec<u8>]) -> io::Result<Vec<(usize, SocketAddr)>> {
+        let mut state = self.state.lock().await;
+        let mut received = vec![];
+        while received.len() < bufs.len() {
+            let Some(timestamp_us) = state.peek().await else {
+                break;
+            };
+            if let Some(speed) = self.speed {
+                let due = state.due(timestamp_us, speed);
+                // Hand out what is due now instead of holding it back.
+                if !received.is_empty() && due > Instant::now() {
+                    break;
+                }
+                sleep_until(due).await;
+            }
+            let record = state.records.pop_front().unwrap();
+            let buf = &mut bufs[received.len()];
+            let size = record.data.len().min(buf.len());
+            buf[..size].copy_from_slice(&record.data[..size]);
+            received.push((size, record.addr));
+        }
+        if received.is_empty() {
+            // Like a socket nobody sends to anymore.
+            self.finished.send_replace(true);
+            drop(state);
+            std::future::pending::<()>().await;
+        }
+        Ok(received)
+    }
+}
+
+impl<R: Read + Send + 'static> Transport for ReplayTransport<R> {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+
+    fn recv_batch<'a>(
+        &'a self,
+        bufs: &'a mut [Vec<u8>],
+    ) -> BoxFuture<'a, io::Result<Vec<(usize, SocketAddr)>>> {
+        Box::pin(self.recv(bufs))
+    }
+
+    fn send_batch<'a>(
+        &'a self,
+        _packets: &'a [Packet],
+    ) -> BoxFuture<'a, Vec<(SocketAddr, io::Error)>> {
+        Box::pin(async { vec![] })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{capture::CaptureWriter, connection::Connection},
+        std::sync::Arc,
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_timing() {
+        let path = std::env::temp_dir().join(format!("replay-{}.cap", std::process::id()));
+        let local: SocketAddr = "10.0.0.1:8001".parse().unwrap();
+        let peer: SocketAddr = "1.2.3.4:8001".parse().unwrap();
+        let writer = CaptureWriter::create(&path, local).unwrap();
+        writer
+            .record_at(1_000_000, Direction::Inbound, [(&[1u8][..], peer)])
+            .unwrap();
+        writer
+            .record_at(1_500_000, Direction::Outbound, [(&[9u8][..], peer)])
+            .unwrap();
+        writer
+            .record_at(3_000_000, Direction::Inbound, [(&[2u8][..], peer)])
+            .unwrap();
+        drop(writer);
+
+        let transport = Arc::new(ReplayTransport::open(&path, Some(2.0)).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        let connection = Connection::with_transport(transport.clone());
+        assert_eq!(connection.local_addr().unwrap(), local);
+        connection.start_receiving();
+
+        let start = Instant::now();
+        let mut rx_in = connection.rx_in.lock().await;
+        assert_eq!(rx_in.recv().await.unwrap(), vec![(vec![1], peer)]);
+        assert_eq!(rx_in.recv().await.unwrap(), vec![(vec![2], peer)]);
+        // Two seconds apart in the capture, one at double speed.
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        transport.finished().await;
+    }
+}
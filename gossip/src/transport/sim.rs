@@ -0,0 +1,231 @@
+use {
+    super::{BoxFuture, Transport},
+    crate::connection::Packet,
+    rand::{rngs::StdRng, Rng, SeedableRng},
+    std::{
+        collections::{HashMap, HashSet},
+        io,
+        net::{Ipv4Addr, SocketAddr},
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+    tokio::sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        Mutex as AsyncMutex,
+    },
+};
+
+const SIM_BASE_PORT: u16 = 8000;
+
+#[derive(Clone, Debug, Default)]
+pub struct SimConfig {
+    pub latency: Duration,
+    // Random extra delay of up to this much per packet, which reorders them.
+    pub jitter: Duration,
+    // Fraction of packets dropped, between 0 and 1.
+    pub loss_rate: f64,
+    pub seed: u64,
+}
+
+struct SimState {
+    config: SimConfig,
+    rng: StdRng,
+    endpoints: HashMap<SocketAddr, UnboundedSender<Packet>>,
+    // Ordered pairs of endpoints that can't reach each other.
+    partitions: HashSet<(SocketAddr, SocketAddr)>,
+    next_port: u16,
+}
+
+// In-memory network for tests. Every drop and delay comes from a seeded rng,
+// so a scenario replays the same way as long as packets are sent in the same
+// order.
+pub struct SimNetwork {
+    state: Mutex<SimState>,
+}
+
+impl SimNetwork {
+    pub fn new(config: SimConfig) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(SimState {
+                rng: StdRng::seed_from_u64(config.seed),
+                config,
+                endpoints: HashMap::new(),
+                partitions: HashSet::new(),
+                next_port: SIM_BASE_PORT,
+            }),
+        })
+    }
+
+    pub fn bind(self: &Arc<Self>) -> Arc<SimTransport> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut state = self.state.lock().unwrap();
+        let addr = SocketAddr::from((Ipv4Addr::new(10, 0, 0, 1), state.next_port));
+        state.next_port += 1;
+        state.endpoints.insert(addr, sender);
+
+        Arc::new(SimTransport {
+            addr,
+            network: Arc::clone(self),
+            receiver: AsyncMutex::new(receiver),
+        })
+    }
+
+    pub fn set_config(&self, config: SimConfig) {
+        self.state.lock().unwrap().config = config;
+    }
+
+    // Cuts every link between `a` and `b`, in both directions.
+    pub fn partition(&self, a: &[SocketAddr], b: &[SocketAddr]) {
+        let mut state = self.state.lock().unwrap();
+        for x in a {
+            for y in b {
+                state.partitions.insert((*x, *y));
+                state.partitions.insert((*y, *x));
+            }
+        }
+    }
+
+    pub fn heal(&self) {
+        self.state.lock().unwrap().partitions.clear();
+    }
+
+    // Like UDP, packets to unknown or unreachable addresses vanish silently.
+    fn deliver(&self, from: SocketAddr, (data, to): &Packet) {
+        let mut state = self.state.lock().unwrap();
+        let Some(sender) = state.endpoints.get(to).cloned() else {
+            return;
+        };
+        if state.partitions.contains(&(from, *to)) {
+            return;
+        }
+        let loss_rate = state.config.loss_rate.clamp(0.0, 1.0);
+        if state.rng.gen_bool(loss_rate) {
+            return;
+        }
+        let jitter = state.config.jitter.mul_f64(state.rng.gen::<f64>());
+        let delay = state.config.latency + jitter;
+
+        let packet = (data.clone(), from);
+        if delay.is_zero() {
+            let _ = sender.send(packet);
+        } else {
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                let _ = sender.send(packet);
+            });
+        }
+    }
+}
+
+pub struct SimTransport {
+    addr: SocketAddr,
+    network: Arc<SimNetwork>,
+    receiver: AsyncMutex<UnboundedReceiver<Packet>>,
+}
+
+impl SimTransport {
+    async fn recv(&self, bufs: &mut [Vec<u8>]) -> io::Result<Vec<(usize, SocketAddr)>> {
+        let mut receiver = self.receiver.lock().await;
+        let first = receiver
+            .recv()
+            .await
+            .ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionAborted))?;
+
+        let mut received = vec![];
+        let mut packet = Some(first);
+        for buf in bufs.iter_mut() {
+            let Some((data, from)) = packet.take().or_else(|| receiver.try_recv().ok()) else {
+                break;
+            };
+            // Oversized datagrams get truncated to the buffer, as with UDP.
+            let size = data.len().min(buf.len());
+            buf[..size].copy_from_slice(&data[..size]);
+            received.push((size, from));
+        }
+        Ok(received)
+    }
+}
+
+impl Transport for SimTransport {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn recv_batch<'a>(
+        &'a self,
+        bufs: &'a mut [Vec<u8>],
+    ) -> BoxFuture<'a, io::Result<Vec<(usize, SocketAddr)>>> {
+        Box::pin(self.recv(bufs))
+    }
+
+    fn send_batch<'a>(
+        &'a self,
+        packets: &'a [Packet],
+    ) -> BoxFuture<'a, Vec<(SocketAddr, io::Error)>> {
+        for packet in packets {
+            self.network.deliver(self.addr, packet);
+        }
+        Box::pin(async { vec![] })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::connection::Connection,
+        tokio::time::{timeout, Instant},
+    };
+
+    async fn recv(connection: &Connection, wait: Duration) -> Vec<Packet> {
+        let mut rx_in = connection.rx_in.lock().await;
+        timeout(wait, rx_in.recv())
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sim_network() {
+        let network = SimNetwork::new(SimConfig {
+            latency: Duration::from_millis(100),
+            ..SimConfig::default()
+        });
+        let a = Connection::with_transport(network.bind());
+        let b = Connection::with_transport(network.bind());
+        for connection in [&a, &b] {
+            connection.start_sending();
+            connection.start_receiving();
+        }
+        let a_addr = a.local_addr().unwrap();
+        let b_addr = b.local_addr().unwrap();
+
+        let start = Instant::now();
+        a.tx_out.send((vec![1], b_addr)).await.unwrap();
+        assert_eq!(
+            recv(&b, Duration::from_secs(1)).await,
+            vec![(vec![1], a_addr)]
+        );
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        network.partition(&[a_addr], &[b_addr]);
+        b.tx_out.send((vec![2], a_addr)).await.unwrap();
+        assert!(recv(&a, Duration::from_secs(1)).await.is_empty());
+
+        network.heal();
+        network.set_config(SimConfig {
+            loss_rate: 1.0,
+            ..SimConfig::default()
+        });
+        b.tx_out.send((vec![3], a_addr)).await.unwrap();
+        assert!(recv(&a, Duration::from_secs(1)).await.is_empty());
+
+        network.set_config(SimConfig::default());
+        b.tx_out.send((vec![4], a_addr)).await.unwrap();
+        assert_eq!(
+            recv(&a, Duration::from_secs(1)).await,
+            vec![(vec![4], b_addr)]
+        );
+    }
+}
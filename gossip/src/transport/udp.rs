@@ -0,0 +1,102 @@
+use {
+    super::{BoxFuture, Transport},
+    crate::connection::{ConnectionConfig, Packet},
+    socket2::SockRef,
+    std::{io, net::SocketAddr},
+    tokio::net::UdpSocket,
+};
+#[cfg(target_os = "linux")]
+use {crate::mmsg, tokio::io::Interest};
+
+pub struct UdpTransport {
+    socket: UdpSocket,
+    // recvmmsg/sendmmsg are Linux only, elsewhere packets go one by one.
+    #[cfg(target_os = "linux")]
+    batch_io: bool,
+}
+
+impl UdpTransport {
+    pub async fn bind(addr: &str, config: &ConnectionConfig) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr).await?;
+        if let Some(size) = config.recv_buffer_size {
+            SockRef::from(&socket).set_recv_buffer_size(size)?;
+        }
+        Ok(Self {
+            socket,
+            #[cfg(target_os = "linux")]
+            batch_io: config.batch_io,
+        })
+    }
+
+    async fn recv(&self, bufs: &mut [Vec<u8>]) -> io::Result<Vec<(usize, SocketAddr)>> {
+        #[cfg(target_os = "linux")]
+        if self.batch_io {
+            return self
+                .socket
+                .async_io(Interest::READABLE, || mmsg::recv_mmsg(&self.socket, bufs))
+                .await;
+        }
+
+        let mut received = vec![self.socket.recv_from(&mut bufs[0]).await?];
+        for buf in &mut bufs[1..] {
+            match self.socket.try_recv_from(buf) {
+                Ok(packet) => received.push(packet),
+                Err(_) => break,
+            }
+        }
+        Ok(received)
+    }
+
+    async fn send(&self, packets: &[Packet]) -> Vec<(SocketAddr, io::Error)> {
+        let mut failed = vec![];
+
+        #[cfg(target_os = "linux")]
+        if self.batch_io {
+            let mut sent = 0;
+            while sent < packets.len() {
+                let result = self
+                    .socket
+                    .async_io(Interest::WRITABLE, || {
+                        mmsg::send_mmsg(&self.socket, &packets[sent..])
+                    })
+                    .await;
+                match result {
+                    Ok(num) => sent += num,
+                    // sendmmsg only fails when the first packet can't be sent.
+                    Err(e) => {
+                        failed.push((packets[sent].1, e));
+                        sent += 1;
+                    }
+                }
+            }
+            return failed;
+        }
+
+        for (msg, addr) in packets {
+            if let Err(e) = self.socket.send_to(msg, addr).await {
+                failed.push((*addr, e));
+            }
+        }
+        failed
+    }
+}
+
+impl Transport for UdpTransport {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    fn recv_batch<'a>(
+        &'a self,
+        bufs: &'a mut [Vec<u8>],
+    ) -> BoxFuture<'a, io::Result<Vec<(usize, SocketAddr)>>> {
+        Box::pin(self.recv(bufs))
+    }
+
+    fn send_batch<'a>(
+        &'a self,
+        packets: &'a [Packet],
+    ) -> BoxFuture<'a, Vec<(SocketAddr, io::Error)>> {
+        Box::pin(self.send(packets))
+    }
+}
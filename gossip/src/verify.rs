@@ -0,0 +1,102 @@
+use {
+    crate::gossip_data::gossip_data::GossipValue,
+    solana_sdk::{pubkey::Pubkey, signature::Signable},
+    std::sync::atomic::{AtomicU64, Ordering},
+    tokio::task,
+};
+
+const VERIFY_BATCH_SIZE: usize = 64;
+
+#[derive(Debug, Default)]
+pub struct VerifyStats {
+    verified: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl VerifyStats {
+    pub fn verified(&self) -> u64 {
+        self.verified.load(Ordering::Relaxed)
+    }
+
+    pub fn failed(&self) -> u64 {
+        self.failed.load(Ordering::Relaxed)
+    }
+}
+
+// Returns the values with valid signatures and the origins of those without.
+pub async fn verify_values(
+    values: Vec<GossipValue>,
+    stats: &VerifyStats,
+) -> (Vec<GossipValue>, Vec<Pubkey>) {
+    let mut values = values.into_iter();
+    let mut handles = vec![];
+    loop {
+        let batch: Vec<GossipValue> = values.by_ref().take(VERIFY_BATCH_SIZE).collect();
+        if batch.is_empty() {
+            break;
+        }
+        handles.push(task::spawn_blocking(move || {
+            batch
+                .into_iter()
+                .map(|value| {
+                    let verified = value.verify();
+                    (value, verified)
+                })
+                .collect::<Vec<_>>()
+        }));
+    }
+
+    let mut verified_values = vec![];
+    let mut failed_origins = vec![];
+    for handle in handles {
+        let batch = match handle.await {
+            Ok(batch) => batch,
+            Err(e) => {
+                log::error!("Verification task failed {:?}", e);
+                continue;
+            }
+        };
+        for (value, verified) in batch {
+            if verified {
+                stats.verified.fetch_add(1, Ordering::Relaxed);
+                verified_values.push(value);
+            } else {
+                stats.failed.fetch_add(1, Ordering::Relaxed);
+                failed_origins.push(value.pubkey());
+            }
+        }
+    }
+    (verified_values, failed_origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::gossip_data::{contact_info::ContactInfo, gossip_data::GossipData},
+        solana_sdk::{signature::Keypair, signer::Signer, timing::timestamp},
+    };
+
+    #[tokio::test]
+    async fn test_verify_values_drops_invalid_signatures() {
+        let keypair = Keypair::new();
+        let gossip = "127.0.0.1:8001".parse().unwrap();
+        let contact_info = ContactInfo::new(keypair.pubkey(), timestamp(), 0, gossip);
+        let valid =
+            GossipValue::new_signed(GossipData::ContactInfo(contact_info.clone()), &keypair);
+        let spoofed =
+            GossipValue::new_signed(GossipData::ContactInfo(contact_info), &Keypair::new());
+
+        let mut values = vec![spoofed; VERIFY_BATCH_SIZE + 1];
+        values.push(valid);
+        let stats = VerifyStats::default();
+
+        let (verified, failed) = verify_values(values, &stats).await;
+
+        assert_eq!(verified.len(), 1);
+        assert_eq!(failed.len(), VERIFY_BATCH_SIZE + 1);
+        assert_eq!(verified[0].pubkey(), keypair.pubkey());
+        assert_eq!(stats.verified(), 1);
+        assert_eq!(stats.failed(), VERIFY_BATCH_SIZE as u64 + 1);
+    }
+}
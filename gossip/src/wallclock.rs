@@ -0,0 +1,248 @@
+use {
+    crate::gossip_data::gossip_data::GossipValue,
+    lru::LruCache,
+    solana_sdk::{
+        clock::{DEFAULT_MS_PER_SLOT, DEFAULT_SLOTS_PER_EPOCH},
+        pubkey::Pubkey,
+    },
+    std::{net::SocketAddr, num::NonZero},
+    thiserror::Error,
+};
+
+pub const DEFAULT_PUSH_WALLCLOCK_WINDOW_MS: u64 = 30_000;
+pub const DEFAULT_PULL_WALLCLOCK_WINDOW_MS: u64 = DEFAULT_SLOTS_PER_EPOCH * DEFAULT_MS_PER_SLOT;
+const SKEW_LOG_CAPACITY: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueSource {
+    Push,
+    PullResponse,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WallclockWindow {
+    pub push_ms: u64,
+    pub pull_ms: u64,
+}
+
+impl Default for WallclockWindow {
+    fn default() -> Self {
+        Self {
+            push_ms: DEFAULT_PUSH_WALLCLOCK_WINDOW_MS,
+            pull_ms: DEFAULT_PULL_WALLCLOCK_WINDOW_MS,
+        }
+    }
+}
+
+impl WallclockWindow {
+    // Pushed values must be fresh in both directions. Pull responses may carry
+    // values signed long ago. Either may be ahead of us by up to the push
+    // window, since the origin's clock can run a little fast.
+    pub fn check(
+        &self,
+        source: ValueSource,
+        wallclock: u64,
+        now: u64,
+    ) -> Result<(), WallclockErrors> {
+        let max_age = match source {
+            ValueSource::Push => self.push_ms,
+            ValueSource::PullResponse => self.pull_ms,
+        };
+        if wallclock > now.saturating_add(self.push_ms) {
+            return Err(WallclockErrors::TooNew {
+                skew_ms: wallclock - now,
+            });
+        }
+        if wallclock < now.saturating_sub(max_age) {
+            return Err(WallclockErrors::TooOld {
+                skew_ms: now - wallclock,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WallclockErrors {
+    #[error("Wallclock is {skew_ms}ms ahead of local time")]
+    TooNew { skew_ms: u64 },
+    #[error("Wallclock is {skew_ms}ms behind local time")]
+    TooOld { skew_ms: u64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SkewReport {
+    pub origin: Pubkey,
+    pub from: SocketAddr,
+    pub source: ValueSource,
+    pub wallclock: u64,
+    pub local_timestamp: u64,
+}
+
+impl SkewReport {
+    pub fn skew_ms(&self) -> i64 {
+        self.wallclock as i64 - self.local_timestamp as i64
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SkewStats {
+    pub rejected: u64,
+    pub last: SkewReport,
+}
+
+pub struct SkewLog {
+    origins: LruCache<Pubkey, SkewStats>,
+}
+
+impl Default for SkewLog {
+    fn default() -> Self {
+        Self::new(NonZero::new(SKEW_LOG_CAPACITY).unwrap())
+    }
+}
+
+impl SkewLog {
+    pub fn new(cap: NonZero<usize>) -> Self {
+        Self {
+            origins: LruCache::new(cap),
+        }
+    }
+
+    pub fn record(&mut self, report: SkewReport) {
+        match self.origins.get_mut(&report.origin) {
+            Some(stats) => {
+                stats.rejected += 1;
+                stats.last = report;
+            }
+            None => {
+                self.origins.put(
+                    report.origin,
+                    SkewStats {
+                        rejected: 1,
+                        last: report,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn get(&self, origin: &Pubkey) -> Option<&SkewStats> {
+        self.origins.peek(origin)
+    }
+
+    pub fn origins(&self) -> impl Iterator<Item = (&Pubkey, &SkewStats)> {
+        self.origins.iter()
+    }
+}
+
+pub fn filter_wallclocks(
+    values: Vec<GossipValue>,
+    source: ValueSource,
+    from: SocketAddr,
+    window: &WallclockWindow,
+    skew_log: &mut SkewLog,
+    now: u64,
+) -> Vec<GossipValue> {
+    values
+        .into_iter()
+        .filter(|value| {
+            let wallclock = value.wallclock();
+            match window.check(source, wallclock, now) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::debug!(
+                        "Dropped {:?} value from:{:?} origin:{} {}",
+                        source,
+                        from,
+                        value.pubkey(),
+                        e
+                    );
+                    skew_log.record(SkewReport {
+                        origin: value.pubkey(),
+                        from,
+                        source,
+                        wallclock,
+                        local_timestamp: now,
+                    });
+                    false
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::gossip_data::{contact_info::ContactInfo, gossip_data::GossipData},
+        solana_sdk::{signature::Keypair, signer::Signer},
+    };
+
+    #[test]
+    fn test_wallclock_window() {
+        let window = WallclockWindow {
+            push_ms: 100,
+            pull_ms: 1_000,
+        };
+        let now = 10_000;
+
+        assert_eq!(window.check(ValueSource::Push, now - 100, now), Ok(()));
+        assert_eq!(window.check(ValueSource::Push, now + 100, now), Ok(()));
+        assert_eq!(
+            window.check(ValueSource::Push, now - 101, now),
+            Err(WallclockErrors::TooOld { skew_ms: 101 })
+        );
+        assert_eq!(
+            window.check(ValueSource::Push, now + 101, now),
+            Err(WallclockErrors::TooNew { skew_ms: 101 })
+        );
+
+        assert_eq!(
+            window.check(ValueSource::PullResponse, now - 1_000, now),
+            Ok(())
+        );
+        assert_eq!(
+            window.check(ValueSource::PullResponse, now - 1_001, now),
+            Err(WallclockErrors::TooOld { skew_ms: 1_001 })
+        );
+        assert_eq!(
+            window.check(ValueSource::PullResponse, now + 50, now),
+            Ok(())
+        );
+        assert_eq!(
+            window.check(ValueSource::PullResponse, now + 101, now),
+            Err(WallclockErrors::TooNew { skew_ms: 101 })
+        );
+    }
+
+    #[test]
+    fn test_filter_wallclocks_reports_origin() {
+        let keypair = Keypair::new();
+        let from: SocketAddr = "127.0.0.1:8001".parse().unwrap();
+        let now = 1_000_000;
+        let values: Vec<GossipValue> = [now, now - 60_000, now + 60_000]
+            .into_iter()
+            .map(|wallclock| {
+                let contact_info = ContactInfo::new(keypair.pubkey(), wallclock, 0, from);
+                GossipValue::new_signed(GossipData::ContactInfo(contact_info), &keypair)
+            })
+            .collect();
+        let mut skew_log = SkewLog::default();
+
+        let values = filter_wallclocks(
+            values,
+            ValueSource::Push,
+            from,
+            &WallclockWindow::default(),
+            &mut skew_log,
+            now,
+        );
+
+        assert_eq!(values.len(), 1);
+        let stats = skew_log.get(&keypair.pubkey()).unwrap();
+        assert_eq!(stats.rejected, 2);
+        assert_eq!(stats.last.from, from);
+        assert_eq!(stats.last.skew_ms(), 60_000);
+    }
+}
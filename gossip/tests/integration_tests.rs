@@ -1,26 +1,79 @@
 use {
     bincode::{deserialize, serialize},
-    dotenv::dotenv,
-    dubstep_gossip::{connection::Connection, ping_pong::Ping, protocol::Protocol},
-    solana_sdk::signature::Keypair,
-    std::{env, net::SocketAddr, time::Duration},
-    tokio::time::timeout,
+    dubstep_gossip::{
+        connection::Connection,
+        events::{EventFilter, GossipEvent, GossipEventKind},
+        gossip_data::{
+            contact_info::ContactInfo,
+            gossip_data::{GossipData, GossipLabel, GossipValue, NodeInstance},
+        },
+        handler::{GossipHandler, HandlerAction},
+        mock_validator::MockValidator,
+        node::{GossipNode, NodeConfig},
+        peer_sampler::gossip_peers,
+        ping_pong::Ping,
+        process_message::process_message,
+        protocol::Protocol,
+    },
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signable},
+        signer::Signer,
+        timing::timestamp,
+    },
+    std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
+    tokio::time::{sleep, timeout},
 };
 
+const LOCALHOST: &str = "127.0.0.1:0";
+
+async fn start_node(entrypoint: &MockValidator) -> Arc<GossipNode> {
+    let connection = Connection::new(LOCALHOST)
+        .await
+        .expect("Failed to create connection");
+    connection.start_sending();
+    connection.start_receiving();
+    let config = NodeConfig {
+        entrypoints: vec![entrypoint.addr()],
+        ..NodeConfig::default()
+    };
+    let node = GossipNode::new(connection, Keypair::new(), config);
+    tokio::spawn(process_message(Arc::clone(&node)));
+    node
+}
+
+async fn wait_for_label(node: &GossipNode, label: GossipLabel) {
+    let found = timeout(Duration::from_secs(10), async {
+        while node.table.read().unwrap().get(&label).is_none() {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await;
+    assert!(found.is_ok(), "Never received {:?}", label);
+}
+
+fn new_node_instance() -> GossipValue {
+    let keypair = Keypair::new();
+    let instance = NodeInstance::new(&mut rand::thread_rng(), keypair.pubkey(), timestamp());
+    GossipValue::new_signed(GossipData::NodeInstance(instance), &keypair)
+}
+
 #[tokio::test]
 async fn test_send_ping() {
-    //if you are using the mainnet or testnet maybe the test will fail
-    //only because the entry point dont respond,
-    //if the test fail you should try again some times to ensure the test really is working or just use the devnet
-    let (udp, entry_point, _, _) = get_env_vars();
-    let connection = Connection::new(&udp)
+    let entrypoint = MockValidator::bind(LOCALHOST, 0)
+        .await
+        .expect("Failed to start mock validator");
+    let connection = Connection::new(LOCALHOST)
         .await
         .expect("Failed to create connection");
 
-    let solana_entrypoint: SocketAddr = entry_point
-        .parse()
-        .expect("Failed create entrypoint socket");
-
     connection.start_sending();
     connection.start_receiving();
 
@@ -29,26 +82,26 @@ async fn test_send_ping() {
 
     let message = serialize(&Protocol::PingMessage(ping)).expect("Failed to serealize ping");
 
-    if let Err(e) = connection.tx_out.send((message, solana_entrypoint)).await {
+    if let Err(e) = connection.tx_out.send((message, entrypoint.addr())).await {
         panic!("Failed to send message: {:?}", e);
     }
 
     let result = timeout(Duration::from_secs(10), async {
         loop {
-            let msg_opt = {
+            let batch = {
                 let mut listen_channel = connection.rx_in.lock().await;
                 listen_channel.recv().await
             };
-            if let Some((msg, from)) = msg_opt {
-                if from == solana_entrypoint {
+            for (msg, from) in batch.into_iter().flatten() {
+                if from == entrypoint.addr() {
                     let protocol: Protocol =
                         deserialize(&msg).expect("Failed to deserialize message");
                     match protocol {
-                        Protocol::PongMessage(_pong) => {
+                        Protocol::PongMessage(pong) if pong.verify() => {
                             return Ok(());
                         }
                         _ => {
-                            return Err("Received a message that is not a Pong");
+                            return Err("Received a message that is not a valid Pong");
                         }
                     }
                 }
@@ -58,21 +111,170 @@ async fn test_send_ping() {
     .await;
 
     assert!(result.is_ok(), "{}", result.unwrap_err());
+    assert_eq!(entrypoint.num_pings(), 1);
 }
 
-fn get_env_vars() -> (String, String, String, String) {
-    dotenv().ok();
+#[tokio::test]
+async fn test_pull_from_entrypoint() {
+    let entrypoint = MockValidator::bind(LOCALHOST, 0)
+        .await
+        .expect("Failed to start mock validator");
+    let value = new_node_instance();
+    entrypoint.add_value(value.clone());
+
+    let node = start_node(&entrypoint).await;
+    let mut events =
+        node.subscribe_with(EventFilter::default().kinds([GossipEventKind::NodeJoined]));
+    node.pull_round()
+        .await
+        .expect("Failed to send pull requests");
+
+    wait_for_label(&node, GossipLabel::ContactInfo(entrypoint.pubkey())).await;
+    let event = timeout(Duration::from_secs(10), events.recv())
+        .await
+        .expect("Never got a NodeJoined event")
+        .unwrap();
+    assert_eq!(event.pubkey(), entrypoint.pubkey());
+    wait_for_label(&node, value.label()).await;
+    assert!(entrypoint.num_pull_requests() > 0);
+}
 
-    let udp_socket =
-        env::var("UDP_SOCKET_TEST").expect("Failed to get UDP_SOCKET_TEST in .env file");
+#[tokio::test]
+async fn test_receive_push() {
+    let entrypoint = MockValidator::bind(LOCALHOST, 0)
+        .await
+        .expect("Failed to start mock validator");
+    let node = start_node(&entrypoint).await;
+    let addr = node.connection.local_addr().unwrap();
 
-    let enrty_point =
-        env::var("ENTRY_POINT_TEST").expect("Failed to get ENTRY_POINT_TEST in .env file");
+    let value = new_node_instance();
+    entrypoint
+        .push(addr, vec![value.clone()])
+        .await
+        .expect("Failed to push values");
 
-    let gossip_socket_addr = env::var("GOSSIP_SOCKET_ADDR_TEST")
-        .expect("Failed to get GOSSIP_SOCKET_ADDR_TEST in .env file");
+    wait_for_label(&node, value.label()).await;
+}
 
-    let peer_node = env::var("PEER_NODE_TEST").expect("Failed to get PEER_NODE_TEST in .env file");
+struct BlockOrigin {
+    blocked: Pubkey,
+    node_instances: AtomicU64,
+}
 
-    (udp_socket, enrty_point, gossip_socket_addr, peer_node)
+impl GossipHandler for BlockOrigin {
+    fn on_value(&self, from: SocketAddr, value: &GossipValue) -> HandlerAction {
+        if value.pubkey() == self.blocked {
+            return HandlerAction::Drop;
+        }
+        dubstep_gossip::handler::dispatch_value(self, from, value)
+    }
+
+    fn on_node_instance(
+        &self,
+        _from: SocketAddr,
+        _value: &GossipValue,
+        _instance: &NodeInstance,
+    ) -> HandlerAction {
+        self.node_instances.fetch_add(1, Ordering::Relaxed);
+        HandlerAction::Continue
+    }
+}
+
+#[tokio::test]
+async fn test_handlers_see_and_drop_values() {
+    let entrypoint = MockValidator::bind(LOCALHOST, 0)
+        .await
+        .expect("Failed to start mock validator");
+    let node = start_node(&entrypoint).await;
+    let addr = node.connection.local_addr().unwrap();
+
+    let blocked = new_node_instance();
+    let allowed = new_node_instance();
+    let handler = Arc::new(BlockOrigin {
+        blocked: blocked.pubkey(),
+        node_instances: AtomicU64::default(),
+    });
+    node.add_handler(handler.clone());
+
+    entrypoint
+        .push(addr, vec![blocked.clone(), allowed.clone()])
+        .await
+        .expect("Failed to push values");
+
+    wait_for_label(&node, allowed.label()).await;
+    assert!(node.table.read().unwrap().get(&blocked.label()).is_none());
+    assert_eq!(handler.node_instances.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn test_rotate_identity() {
+    let entrypoint = MockValidator::bind(LOCALHOST, 0)
+        .await
+        .expect("Failed to start mock validator");
+    let node = start_node(&entrypoint).await;
+    let old_pubkey = node.pubkey();
+    let gossip = node.contact_info().gossip();
+    let instance = node.instance();
+    // Puts our own values in the table.
+    node.push_round().await;
+    assert!(
+        gossip_peers(&node.table.read().unwrap(), &Pubkey::new_unique(), 0)
+            .iter()
+            .any(|peer| peer.pubkey == old_pubkey)
+    );
+
+    let keypair = Keypair::new();
+    let pubkey = keypair.pubkey();
+    node.set_keypair(keypair);
+    assert_eq!(node.pubkey(), pubkey);
+    assert_eq!(node.contact_info().gossip(), gossip);
+    assert_ne!(node.instance().token(), instance.token());
+    for value in node.own_values() {
+        assert_eq!(value.pubkey(), pubkey);
+        assert!(value.verify());
+    }
+    // Our old self is gone, not taken for a peer.
+    let table = node.table.read().unwrap();
+    assert!(gossip_peers(&table, &pubkey, 0)
+        .iter()
+        .all(|peer| peer.pubkey != old_pubkey));
+    assert!(table
+        .entries()
+        .all(|entry| entry.value.pubkey() != old_pubkey));
+}
+
+#[tokio::test]
+async fn test_purge_node_left() {
+    let entrypoint = MockValidator::bind(LOCALHOST, 0)
+        .await
+        .expect("Failed to start mock validator");
+    let node = start_node(&entrypoint).await;
+    let left = Keypair::new();
+    let contact_info = ContactInfo::new(left.pubkey(), timestamp(), 0, entrypoint.addr());
+    let instance = NodeInstance::new(&mut rand::thread_rng(), left.pubkey(), timestamp());
+    {
+        let mut table = node.table.write().unwrap();
+        for value in [
+            GossipValue::new_signed(GossipData::ContactInfo(contact_info), &left),
+            GossipValue::new_signed(GossipData::NodeInstance(instance), &left),
+            new_node_instance(),
+        ] {
+            // Received long ago, so purged right away.
+            table.insert(value, 0).expect("Failed to insert");
+        }
+    }
+    let mut events = node.subscribe_with(EventFilter::default().kinds([GossipEventKind::NodeLeft]));
+
+    assert_eq!(node.purge_round(), 3);
+    let done = Pubkey::new_unique();
+    node.emit(vec![GossipEvent::NodeLeft(done)]);
+    let mut left_pubkeys = vec![];
+    loop {
+        match events.recv().await.expect("Failed to receive event") {
+            GossipEvent::NodeLeft(pubkey) if pubkey == done => break,
+            GossipEvent::NodeLeft(pubkey) => left_pubkeys.push(pubkey),
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+    assert_eq!(left_pubkeys, vec![left.pubkey()]);
 }
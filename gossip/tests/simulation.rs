@@ -0,0 +1,64 @@
+use {
+    dubstep_gossip::{
+        connection::Connection,
+        gossip_data::gossip_data::{GossipData, GossipLabel, GossipValue},
+        node::{GossipNode, NodeConfig},
+        process_message::process_message,
+        transport::sim::{SimConfig, SimNetwork},
+    },
+    solana_sdk::{signature::Keypair, timing::timestamp},
+    std::{sync::Arc, time::Duration},
+};
+
+fn start_node(network: &Arc<SimNetwork>) -> Arc<GossipNode> {
+    let connection = Connection::with_transport(network.bind());
+    connection.start_sending();
+    connection.start_receiving();
+    let node = GossipNode::new(connection, Keypair::new(), NodeConfig::default());
+    tokio::spawn(process_message(Arc::clone(&node)));
+    node
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_push_between_simulated_nodes() {
+    let network = SimNetwork::new(SimConfig {
+        latency: Duration::from_millis(20),
+        jitter: Duration::from_millis(30),
+        loss_rate: 0.1,
+        seed: 42,
+    });
+    let nodes: Vec<Arc<GossipNode>> = (0..4).map(|_| start_node(&network)).collect();
+
+    // Every node starts out knowing everyone else's contact info.
+    for node in &nodes {
+        let mut table = node.table.write().unwrap();
+        for other in &nodes {
+            let contact_info = GossipData::ContactInfo(other.contact_info());
+            let value = GossipValue::new_signed(contact_info, &other.keypair());
+            table.insert(value, timestamp()).unwrap();
+        }
+    }
+
+    // The first rounds only ping, pushes start once pongs are back. Lost pings
+    // are retried after the ping cache rate limit.
+    for _ in 0..60 {
+        for node in &nodes {
+            node.rotate_active_set().await;
+            node.push_round().await;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    for node in &nodes {
+        let table = node.table.read().unwrap();
+        for other in &nodes {
+            let label = GossipLabel::NodeInstance(other.pubkey());
+            assert!(
+                table.get(&label).is_some(),
+                "{} never got the node instance of {}",
+                node.pubkey(),
+                other.pubkey()
+            );
+        }
+    }
+}